@@ -1,5 +1,6 @@
 extern crate case;
 use rand::prelude::*;
+use crate::utils;
 
 use case::CaseExt;
 
@@ -15,7 +16,7 @@ fn title_case(text: &str) -> String {
         let words: Vec<String> = line.split_whitespace().map(|x| x.to_string()).collect();
         for word in words {
             let lower_word = word.to_lowercase();
-            let first_char = lower_word.chars().nth(0).unwrap_or_default().to_uppercase().to_string();
+            let first_char = lower_word.chars().next().unwrap_or_default().to_uppercase().to_string();
             let temp = first_char + &lower_word[1..];
             temp_result.push(temp);
         }
@@ -32,17 +33,16 @@ fn upper_case(text: &str) -> String {
     return text.to_uppercase();
 }
 
-fn sponge_case(text: &str) -> String {
+fn sponge_case(text: &str, rng: &mut StdRng) -> String {
     let mut result = Vec::<String>::new();
     let lines: Vec<String>  = text.lines().map(|x| x.to_string()).collect();
     for line in lines {
         let mut temp_result = Vec::<String>::new();
         let words: Vec<String> = line.split_whitespace().map(|x| x.to_string()).collect();
-        let mut rng = rand::rng();
         for word in words {
             let temp = word.chars().map(|c| 
-                if rng.random_bool(0.5) { c.to_lowercase().nth(0).unwrap() } 
-                else { c.to_uppercase().nth(0).unwrap() }
+                if rng.random_bool(0.5) { c.to_lowercase().next().unwrap() } 
+                else { c.to_uppercase().next().unwrap() }
             ).collect::<String>();
             temp_result.push(temp);
         }
@@ -87,12 +87,12 @@ pub fn select_case_option(args: &Vec<String>) -> CaseOp {
     return CaseOp::Unknown;
 }
 
-pub fn handle_case_operation(text: &str, op: CaseOp) -> String {
+pub fn handle_case_operation(text: &str, op: CaseOp, args: &Vec<String>) -> String {
     return match op {
         CaseOp::Title => title_case(text),
         CaseOp::Lower => lower_case(text),
         CaseOp::Upper => upper_case(text),
-        CaseOp::Sponge => sponge_case(text),
+        CaseOp::Sponge => sponge_case(text, &mut utils::get_rng(args)),
         CaseOp::Snake => snake_case(text),
         CaseOp::Camel => camel_case(text),
         CaseOp::Kebab => kebab_case(text),
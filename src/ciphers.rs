@@ -0,0 +1,123 @@
+const MORSE: &[(char, &str)] = &[
+    ('A', ".-"), ('B', "-..."), ('C', "-.-."), ('D', "-.."), ('E', "."), ('F', "..-."),
+    ('G', "--."), ('H', "...."), ('I', ".."), ('J', ".---"), ('K', "-.-"), ('L', ".-.."),
+    ('M', "--"), ('N', "-."), ('O', "---"), ('P', ".--."), ('Q', "--.-"), ('R', ".-."),
+    ('S', "..."), ('T', "-"), ('U', "..-"), ('V', "...-"), ('W', ".--"), ('X', "-..-"),
+    ('Y', "-.--"), ('Z', "--.."), ('0', "-----"), ('1', ".----"), ('2', "..---"),
+    ('3', "...--"), ('4', "....-"), ('5', "....."), ('6', "-...."), ('7', "--..."),
+    ('8', "---.."), ('9', "----."), ('.', ".-.-.-"), (',', "--..--"), ('?', "..--.."),
+    ('\'', ".----."), ('!', "-.-.--"), ('/', "-..-."), ('(', "-.--."), (')', "-.--.-"),
+    ('&', ".-..."), (':', "---..."), (';', "-.-.-."), ('=', "-...-"), ('+', ".-.-."),
+    ('-', "-....-"), ('_', "..--.-"), ('"', ".-..-."), ('$', "...-..-"), ('@', ".--.-."),
+];
+
+const NATO: &[(char, &str)] = &[
+    ('A', "Alfa"), ('B', "Bravo"), ('C', "Charlie"), ('D', "Delta"), ('E', "Echo"),
+    ('F', "Foxtrot"), ('G', "Golf"), ('H', "Hotel"), ('I', "India"), ('J', "Juliett"),
+    ('K', "Kilo"), ('L', "Lima"), ('M', "Mike"), ('N', "November"), ('O', "Oscar"),
+    ('P', "Papa"), ('Q', "Quebec"), ('R', "Romeo"), ('S', "Sierra"), ('T', "Tango"),
+    ('U', "Uniform"), ('V', "Victor"), ('W', "Whiskey"), ('X', "X-ray"), ('Y', "Yankee"),
+    ('Z', "Zulu"), ('0', "Zero"), ('1', "One"), ('2', "Two"), ('3', "Three"), ('4', "Four"),
+    ('5', "Five"), ('6', "Six"), ('7', "Seven"), ('8', "Eight"), ('9', "Nine"),
+];
+
+fn shift_letter(c: char, shift: i32) -> char {
+    let base = match c {
+        'A'..='Z' => b'A',
+        'a'..='z' => b'a',
+        _ => return c,
+    };
+    return (((c as u8 - base) as i32 + shift).rem_euclid(26) as u8 + base) as char;
+}
+
+/// Caesar shift of the ASCII letters; a negative shift decodes.
+pub fn rot_n(text: &str, shift: i32) -> String {
+    return text.chars().map(|c| shift_letter(c, shift)).collect();
+}
+
+/// Rotates every printable ASCII character from `!` to `~` by 47.
+pub fn rot47(text: &str) -> String {
+    return text.chars().map(|c| match c {
+        '!'..='~' => (((c as u8 - b'!' + 47) % 94) + b'!') as char,
+        _ => c,
+    }).collect();
+}
+
+pub fn atbash(text: &str) -> String {
+    return text.chars().map(|c| match c {
+        'A'..='Z' => (b'Z' - (c as u8 - b'A')) as char,
+        'a'..='z' => (b'z' - (c as u8 - b'a')) as char,
+        _ => c,
+    }).collect();
+}
+
+/// Only letters advance the key, so spacing and punctuation pass through unchanged.
+pub fn vigenere(text: &str, key: &str, decode: bool) -> String {
+    let shifts: Vec<i32> = key.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| (c.to_ascii_lowercase() as u8 - b'a') as i32)
+        .collect();
+    if shifts.is_empty() {
+        panic!("The Vigenère key must contain at least one letter.");
+    }
+    let mut idx = 0;
+    return text.chars().map(|c| {
+        if !c.is_ascii_alphabetic() { return c; }
+        let shift = shifts[idx % shifts.len()];
+        idx += 1;
+        shift_letter(c, if decode { -shift } else { shift })
+    }).collect();
+}
+
+fn xor_bytes(data: &[u8], key: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        panic!("The XOR key must not be empty.");
+    }
+    return data.iter().zip(key.iter().cycle()).map(|(b, k)| b ^ k).collect();
+}
+
+/// The result is usually not printable, so it is written as hex.
+pub fn xor_encode(text: &str, key: &str) -> String {
+    return hex::encode(xor_bytes(text.as_bytes(), key.as_bytes()));
+}
+
+pub fn xor_decode(text: &str, key: &str) -> String {
+    let clean: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let data = hex::decode(clean).expect("XOR input must be hex (as written by xor_encode).");
+    return String::from_utf8_lossy(&xor_bytes(&data, key.as_bytes())).to_string();
+}
+
+/// Letters are separated by spaces and words by ` / `; unknown characters are dropped.
+pub fn morse_encode(text: &str) -> String {
+    return text.lines().map(|line| {
+        line.split_whitespace().map(|word| {
+            word.chars()
+                .filter_map(|c| MORSE.iter().find(|(k, _)| *k == c.to_ascii_uppercase()).map(|(_, m)| *m))
+                .collect::<Vec<&str>>()
+                .join(" ")
+        }).collect::<Vec<String>>().join(" / ")
+    }).collect::<Vec<String>>().join("\n");
+}
+
+/// Unrecognised codes come back as `?` so their position is not lost.
+pub fn morse_decode(text: &str) -> String {
+    return text.lines().map(|line| {
+        line.split('/').map(|word| {
+            word.split_whitespace()
+                .map(|code| MORSE.iter().find(|(_, m)| *m == code).map_or('?', |(k, _)| *k))
+                .collect::<String>()
+        }).collect::<Vec<String>>().join(" ").trim().to_string()
+    }).collect::<Vec<String>>().join("\n");
+}
+
+/// Spells each letter and digit with the NATO alphabet, marking word breaks with `/`.
+pub fn nato(text: &str) -> String {
+    return text.lines().map(|line| {
+        line.split_whitespace().map(|word| {
+            word.chars().map(|c| match NATO.iter().find(|(k, _)| *k == c.to_ascii_uppercase()) {
+                Some((_, name)) => name.to_string(),
+                None => c.to_string(),
+            }).collect::<Vec<String>>().join(" ")
+        }).collect::<Vec<String>>().join(" / ")
+    }).collect::<Vec<String>>().join("\n");
+}
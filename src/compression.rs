@@ -0,0 +1,176 @@
+use std::io::{Read, Write};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use crate::utils;
+
+pub enum CompressionOp {
+    GzipCompress, GzipDecompress, ZlibCompress, ZlibDecompress, DeflateCompress,
+    DeflateDecompress, Bzip2Compress, Bzip2Decompress, XzCompress, XzDecompress, ZstdCompress,
+    ZstdDecompress, Unknown,
+}
+
+fn str_to_compression_op(arg: &str) -> CompressionOp {
+    return match arg {
+        "gzip_compress" => CompressionOp::GzipCompress,
+        "gzip_decompress" => CompressionOp::GzipDecompress,
+        "zlib_compress" => CompressionOp::ZlibCompress,
+        "zlib_decompress" => CompressionOp::ZlibDecompress,
+        "deflate_compress" => CompressionOp::DeflateCompress,
+        "deflate_decompress" => CompressionOp::DeflateDecompress,
+        "bzip2_compress" => CompressionOp::Bzip2Compress,
+        "bzip2_decompress" => CompressionOp::Bzip2Decompress,
+        "xz_compress" => CompressionOp::XzCompress,
+        "xz_decompress" => CompressionOp::XzDecompress,
+        "zstd_compress" => CompressionOp::ZstdCompress,
+        "zstd_decompress" => CompressionOp::ZstdDecompress,
+        _ => CompressionOp::Unknown,
+    }
+}
+
+pub fn select_compression_option(args: &Vec<String>) -> CompressionOp {
+    let compression_options: Vec<String> = vec!["gzip_compress", "gzip_decompress", "zlib_compress", "zlib_decompress", "deflate_compress", "deflate_decompress", "bzip2_compress", "bzip2_decompress", "xz_compress", "xz_decompress", "zstd_compress", "zstd_decompress"].into_iter().map(|x| x.to_string()).collect();
+    for arg in args {
+        if compression_options.contains(&arg.to_lowercase()) {
+            return str_to_compression_op(arg);
+        }
+    }
+    return CompressionOp::Unknown
+}
+
+pub fn is_compress(op: &CompressionOp) -> bool {
+    return matches!(
+        op,
+        CompressionOp::GzipCompress | CompressionOp::ZlibCompress | CompressionOp::DeflateCompress
+            | CompressionOp::Bzip2Compress | CompressionOp::XzCompress | CompressionOp::ZstdCompress
+    );
+}
+
+/// The file extension that compressed output is written with.
+pub fn extension(op: &CompressionOp) -> &'static str {
+    return match op {
+        CompressionOp::GzipCompress | CompressionOp::GzipDecompress => "gz",
+        CompressionOp::ZlibCompress | CompressionOp::ZlibDecompress => "zz",
+        CompressionOp::DeflateCompress | CompressionOp::DeflateDecompress => "deflate",
+        CompressionOp::Bzip2Compress | CompressionOp::Bzip2Decompress => "bz2",
+        CompressionOp::XzCompress | CompressionOp::XzDecompress => "xz",
+        CompressionOp::ZstdCompress | CompressionOp::ZstdDecompress => "zst",
+        CompressionOp::Unknown => "",
+    };
+}
+
+/// The extension added to or stripped from the file name; base64 output gets a `.b64`
+/// suffix on top, e.g. `.gz.b64`, since it is text rather than the binary format.
+pub fn file_extension(op: &CompressionOp, args: &Vec<String>) -> String {
+    if utils::has_option(args, "base64") {
+        return format!("{}.b64", extension(op));
+    }
+    return extension(op).to_string();
+}
+
+/// Works on raw bytes. `--base64` armours the binary side, so compressed output is
+/// written as base64 text and decompression expects base64 input (for example a
+/// Kubernetes secret or a SAML payload). `--level=<n>` sets the compression level.
+/// Decompressed output is limited to `--max-size=<MiB>` (1024 by default), so a small
+/// crafted stream cannot expand until memory runs out.
+pub fn handle_compression_operation(data: &[u8], op: &CompressionOp, args: &Vec<String>) -> Vec<u8> {
+    let armoured = utils::has_option(args, "base64");
+    let decoded;
+    let input = if armoured && !is_compress(op) {
+        let clean: String = String::from_utf8_lossy(data).chars().filter(|c| !c.is_whitespace()).collect();
+        decoded = STANDARD.decode(clean).unwrap_or_else(|e| panic!("Input is not valid base64: {}", e));
+        decoded.as_slice()
+    }
+    else { data };
+    let level = select_level(op, args);
+    let limit = utils::get_usize_option(args, "max-size", 1024) as u64 * 1024 * 1024;
+    let result = match op {
+        CompressionOp::GzipCompress => write_all(GzEncoder::new(Vec::new(), flate_level(level)), input),
+        CompressionOp::GzipDecompress => read_all(GzDecoder::new(input), limit),
+        CompressionOp::ZlibCompress => write_all(ZlibEncoder::new(Vec::new(), flate_level(level)), input),
+        CompressionOp::ZlibDecompress => read_all(ZlibDecoder::new(input), limit),
+        CompressionOp::DeflateCompress => write_all(DeflateEncoder::new(Vec::new(), flate_level(level)), input),
+        CompressionOp::DeflateDecompress => read_all(DeflateDecoder::new(input), limit),
+        CompressionOp::Bzip2Compress => write_all(bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::new(level.map_or(9, |l| l as u32))), input),
+        CompressionOp::Bzip2Decompress => read_all(bzip2::read::MultiBzDecoder::new(input), limit),
+        CompressionOp::XzCompress => write_all(xz2::write::XzEncoder::new(Vec::new(), level.map_or(6, |l| l as u32)), input),
+        CompressionOp::XzDecompress => read_all(xz2::read::XzDecoder::new_multi_decoder(input), limit),
+        CompressionOp::ZstdCompress => zstd::encode_all(input, level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL)),
+        CompressionOp::ZstdDecompress => zstd::stream::read::Decoder::new(input).and_then(|decoder| read_all(decoder, limit)),
+        CompressionOp::Unknown => panic!("Unknown compression operation specified."),
+    };
+    let output = match result {
+        Ok(output) => output,
+        Err(e) if is_compress(op) => panic!("Unable to compress data: {}", e),
+        Err(e) if e.kind() == std::io::ErrorKind::FileTooLarge => panic!("Unable to decompress data: {}", e),
+        Err(e) => panic!("Unable to decompress data (is it really {}?): {}", extension(op), e),
+    };
+    if armoured && is_compress(op) {
+        return STANDARD.encode(output).into_bytes();
+    }
+    return output;
+}
+
+fn flate_level(level: Option<i32>) -> Compression {
+    return level.map(|l| Compression::new(l as u32)).unwrap_or_default();
+}
+
+/// `--level=<n>`, checked against what the algorithm accepts so a bad value is reported
+/// here rather than failing inside the library.
+fn select_level(op: &CompressionOp, args: &Vec<String>) -> Option<i32> {
+    let level = utils::get_option_value(args, "level")?.parse::<i32>().expect("--level expects a whole number.");
+    let (name, range) = match op {
+        CompressionOp::GzipCompress => ("gzip", 0..=9),
+        CompressionOp::ZlibCompress => ("zlib", 0..=9),
+        CompressionOp::DeflateCompress => ("deflate", 0..=9),
+        CompressionOp::Bzip2Compress => ("bzip2", 1..=9),
+        CompressionOp::XzCompress => ("xz", 0..=9),
+        CompressionOp::ZstdCompress => ("zstd", zstd::compression_level_range()),
+        _ => return Some(level),
+    };
+    if !range.contains(&level) {
+        panic!("--level for {} must be between {} and {}.", name, range.start(), range.end());
+    }
+    return Some(level);
+}
+
+/// Every encoder used here is a writer that hands back its buffer from `finish`.
+trait Finish: Write {
+    fn finish_into(self) -> std::io::Result<Vec<u8>>;
+}
+
+impl Finish for GzEncoder<Vec<u8>> {
+    fn finish_into(self) -> std::io::Result<Vec<u8>> { self.finish() }
+}
+
+impl Finish for ZlibEncoder<Vec<u8>> {
+    fn finish_into(self) -> std::io::Result<Vec<u8>> { self.finish() }
+}
+
+impl Finish for DeflateEncoder<Vec<u8>> {
+    fn finish_into(self) -> std::io::Result<Vec<u8>> { self.finish() }
+}
+
+impl Finish for bzip2::write::BzEncoder<Vec<u8>> {
+    fn finish_into(self) -> std::io::Result<Vec<u8>> { self.finish() }
+}
+
+impl Finish for xz2::write::XzEncoder<Vec<u8>> {
+    fn finish_into(self) -> std::io::Result<Vec<u8>> { self.finish() }
+}
+
+fn write_all<E: Finish>(mut encoder: E, input: &[u8]) -> std::io::Result<Vec<u8>> {
+    encoder.write_all(input)?;
+    return encoder.finish_into();
+}
+
+fn read_all<R: Read>(decoder: R, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    decoder.take(limit + 1).read_to_end(&mut output)?;
+    if output.len() as u64 > limit {
+        let message = format!("output is larger than {} MiB (raise the limit with --max-size=<MiB>)", limit / 1024 / 1024);
+        return Err(std::io::Error::new(std::io::ErrorKind::FileTooLarge, message));
+    }
+    return Ok(output);
+}
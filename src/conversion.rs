@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use serde_json::Value;
 use std::collections::BTreeSet;
+use crate::json;
 
 pub enum ConversionOp {
     AsciiToHex, HexToAscii, YamlToJson, JsonToYaml, DateToTimestamp, DateToUtc,
     BinaryToDecimal, DecimalToBinary, JsonToQueryString, QueryStringToJson, DecimalToHex,
-    HexToDecimal, JsonToCsv, CsvToJson, HexToRgb, FishPathHexConv, Unknown,
+    HexToDecimal, JsonToCsv, CsvToJson, HexToRgb, FishPathHexConv, Json5ToJson, Unknown,
 }
 
 fn str_to_conversion_op(arg: &str) -> ConversionOp {
@@ -26,12 +27,13 @@ fn str_to_conversion_op(arg: &str) -> ConversionOp {
        "csv_to_json" => ConversionOp::CsvToJson,
        "hex_to_rgb" => ConversionOp::HexToRgb,
        "fish_path_hex_conv" => ConversionOp::FishPathHexConv,
+       "json5_to_json" => ConversionOp::Json5ToJson,
         _ => ConversionOp::Unknown,
     }
 }
 
 pub fn select_conversion_option(args: &Vec<String>) -> ConversionOp {
-    let conversion_options: Vec<String> = vec!["ascii_to_hex", "hex_to_ascii", "yaml_to_json", "json_to_yaml", "date_to_timestamp", "date_to_utc", "binary_to_decimal", "decimal_to_binary", "json_to_query_string", "query_strong_to_json", "decimal_to_hex", "hex_to_decimal", "json_to_csv", "csv_to_json", "hex_to_rgb", "fish_path_hex_conv"].into_iter().map(|x| x.to_string()).collect();
+    let conversion_options: Vec<String> = vec!["ascii_to_hex", "hex_to_ascii", "yaml_to_json", "json_to_yaml", "date_to_timestamp", "date_to_utc", "binary_to_decimal", "decimal_to_binary", "json_to_query_string", "query_strong_to_json", "decimal_to_hex", "hex_to_decimal", "json_to_csv", "csv_to_json", "hex_to_rgb", "fish_path_hex_conv", "json5_to_json"].into_iter().map(|x| x.to_string()).collect();
     for arg in args {
         if conversion_options.contains(&arg.to_lowercase()) {
             return str_to_conversion_op(arg);
@@ -58,6 +60,7 @@ pub fn handle_conversion_operation(text: &str, op: &ConversionOp) -> String {
         ConversionOp::CsvToJson => csv_to_json(text),
         ConversionOp::HexToRgb => hex_to_rgb(text),
         ConversionOp::FishPathHexConv => fish_path_hex_conv(text),
+        ConversionOp::Json5ToJson => json5_to_json(text),
         ConversionOp::Unknown => panic!("Unknown conversion operation specified."),
     };
 }
@@ -77,6 +80,15 @@ fn hex_to_ascii(text: &str) -> String {
     panic!("Invalid hex provided.");
 }
 
+/// Also covers JSONC; comments are dropped and JSON5-only syntax is rewritten as JSON.
+fn json5_to_json(text: &str) -> String {
+    let format = json::JsonFormat { indent: "  ".to_string(), sort_keys: false, compact_arrays: false, ascii: false, comments: false };
+    return match json::parse_lenient(text) {
+        Ok(document) => json::to_pretty(&document, &format),
+        Err(e) => panic!("Unable to read JSON5 data: {}", e),
+    };
+}
+
 fn yaml_to_json(text: &str) -> String {
     match serde_yaml::from_str::<serde_json::Value>(text) {
         Ok(value) => match serde_json::to_string_pretty(&value) {
@@ -221,11 +233,5 @@ fn hex_to_rgb(text: &str) -> String {
 }
 
 fn fish_path_hex_conv(text: &str) -> String {
-    return {
-        text
-            .bytes()
-            .map(|b| format!("\\x{:02x}", b))
-            .collect::<String>()
-    };
+    return crate::escapes::escape_hex(text, true);
 }
-
@@ -0,0 +1,225 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::ChaCha20Poly1305;
+use sha2::Sha256;
+
+const BEGIN_MARKER: &str = "-----BEGIN BDP ENCRYPTED MESSAGE-----";
+const END_MARKER: &str = "-----END BDP ENCRYPTED MESSAGE-----";
+const ARGON2_M_COST: u32 = 19456;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+const PBKDF2_ROUNDS: u32 = 600_000;
+/// Upper bounds for parameters read from a message header, which are used before the
+/// tag can be checked: 1 GiB of memory and about sixteen times the default rounds.
+const ARGON2_MAX_M_COST: u32 = 1 << 20;
+const ARGON2_MAX_T_COST: u32 = 32;
+const ARGON2_MAX_P_COST: u32 = 16;
+const PBKDF2_MAX_ROUNDS: u32 = 10_000_000;
+
+pub enum Cipher {
+    Aes256Gcm, ChaCha20Poly1305,
+}
+
+pub enum PasswordHashAlg {
+    Bcrypt, Scrypt, Argon2id, Pbkdf2,
+}
+
+pub enum Kdf {
+    Argon2id { m_cost: u32, t_cost: u32, p_cost: u32 },
+    Pbkdf2Sha256 { rounds: u32 },
+}
+
+pub fn str_to_cipher(arg: &str) -> Cipher {
+    return match arg.to_lowercase().as_str() {
+        "aes" | "aes-256-gcm" | "aes256gcm" => Cipher::Aes256Gcm,
+        "chacha" | "chacha20" | "chacha20-poly1305" | "chacha20poly1305" => Cipher::ChaCha20Poly1305,
+        _ => panic!("Unknown cipher '{}'. Use aes-256-gcm or chacha20-poly1305.", arg),
+    }
+}
+
+pub fn str_to_kdf(arg: &str) -> Kdf {
+    return match arg.to_lowercase().as_str() {
+        "argon2" | "argon2id" => Kdf::Argon2id { m_cost: ARGON2_M_COST, t_cost: ARGON2_T_COST, p_cost: ARGON2_P_COST },
+        "pbkdf2" | "pbkdf2-sha256" => Kdf::Pbkdf2Sha256 { rounds: PBKDF2_ROUNDS },
+        _ => panic!("Unknown key derivation function '{}'. Use argon2 or pbkdf2.", arg),
+    }
+}
+
+fn cipher_name(cipher: &Cipher) -> &'static str {
+    return match cipher {
+        Cipher::Aes256Gcm => "AES-256-GCM",
+        Cipher::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+    };
+}
+
+fn kdf_description(kdf: &Kdf) -> String {
+    return match kdf {
+        Kdf::Argon2id { m_cost, t_cost, p_cost } => format!("Argon2id m={},t={},p={}", m_cost, t_cost, p_cost),
+        Kdf::Pbkdf2Sha256 { rounds } => format!("PBKDF2-SHA256 i={}", rounds),
+    };
+}
+
+/// Reads back the `KDF:` header written by `kdf_description`. The header is not
+/// authenticated until after the key is derived, so each parameter must lie within
+/// `1..=max` to stop a crafted message from demanding unbounded memory or time.
+fn parse_kdf(text: &str) -> Result<Kdf, String> {
+    let (name, params) = text.split_once(' ').unwrap_or((text, ""));
+    let param = |key: &str, max: u32| -> Result<u32, String> {
+        let value: u32 = params.split(',')
+            .find_map(|p| p.strip_prefix(&format!("{}=", key)))
+            .and_then(|v| v.parse().ok())
+            .ok_or(format!("Missing '{}' parameter for {}.", key, name))?;
+        if value == 0 || value > max {
+            return Err(format!("{} parameter '{}' is {}, outside the accepted range 1 to {}.", name, key, value, max));
+        }
+        return Ok(value);
+    };
+    return match name {
+        "Argon2id" => Ok(Kdf::Argon2id {
+            m_cost: param("m", ARGON2_MAX_M_COST)?,
+            t_cost: param("t", ARGON2_MAX_T_COST)?,
+            p_cost: param("p", ARGON2_MAX_P_COST)?,
+        }),
+        "PBKDF2-SHA256" => Ok(Kdf::Pbkdf2Sha256 { rounds: param("i", PBKDF2_MAX_ROUNDS)? }),
+        _ => Err(format!("Unsupported key derivation function '{}'.", name)),
+    };
+}
+
+fn derive_key(password: &str, salt: &[u8], kdf: &Kdf) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    match kdf {
+        Kdf::Argon2id { m_cost, t_cost, p_cost } => {
+            let params = Params::new(*m_cost, *t_cost, *p_cost, Some(key.len())).map_err(|e| e.to_string())?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(password.as_bytes(), salt, &mut key)
+                .map_err(|e| e.to_string())?;
+        },
+        Kdf::Pbkdf2Sha256 { rounds } => pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, *rounds, &mut key),
+    }
+    return Ok(key);
+}
+
+/// The header lines double as associated data, so tampering with the
+/// cipher or KDF parameters makes decryption fail rather than silently misbehave.
+fn seal(cipher: &Cipher, key: &[u8; 32], nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    let payload = Payload { msg, aad };
+    let nonce = Nonce::from_slice(nonce);
+    let result = match cipher {
+        Cipher::Aes256Gcm => Aes256Gcm::new_from_slice(key).unwrap().encrypt(nonce, payload),
+        Cipher::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key).unwrap().encrypt(nonce, payload),
+    };
+    return result.map_err(|_| "Encryption failed.".to_string());
+}
+
+fn open(cipher: &Cipher, key: &[u8; 32], nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    let payload = Payload { msg, aad };
+    let nonce = Nonce::from_slice(nonce);
+    let result = match cipher {
+        Cipher::Aes256Gcm => Aes256Gcm::new_from_slice(key).unwrap().decrypt(nonce, payload),
+        Cipher::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key).unwrap().decrypt(nonce, payload),
+    };
+    return result.map_err(|_| "Decryption failed: wrong password or corrupted message.".to_string());
+}
+
+fn wrap_lines(text: &str, width: usize) -> String {
+    return text.as_bytes().chunks(width).map(|c| String::from_utf8_lossy(c).to_string()).collect::<Vec<String>>().join("\n");
+}
+
+/// Produces an armoured block whose headers record everything needed to decrypt
+/// apart from the password:
+///
+/// ```text
+/// -----BEGIN BDP ENCRYPTED MESSAGE-----
+/// Cipher: AES-256-GCM
+/// KDF: Argon2id m=19456,t=2,p=1
+/// Salt: <base64>
+/// Nonce: <base64>
+///
+/// <base64 ciphertext>
+/// -----END BDP ENCRYPTED MESSAGE-----
+/// ```
+pub fn encrypt(text: &str, password: &str, cipher: Cipher, kdf: Kdf) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::fill(&mut salt);
+    rand::fill(&mut nonce);
+    let key = derive_key(password, &salt, &kdf)?;
+    let headers = [
+        format!("Cipher: {}", cipher_name(&cipher)),
+        format!("KDF: {}", kdf_description(&kdf)),
+        format!("Salt: {}", STANDARD.encode(salt)),
+        format!("Nonce: {}", STANDARD.encode(nonce)),
+    ].join("\n");
+    let sealed = seal(&cipher, &key, &nonce, text.as_bytes(), headers.as_bytes())?;
+    let body = wrap_lines(&STANDARD.encode(sealed), 64);
+    return Ok(format!("{}\n{}\n\n{}\n{}", BEGIN_MARKER, headers, body, END_MARKER));
+}
+
+pub fn decrypt(text: &str, password: &str) -> Result<String, String> {
+    let start = text.find(BEGIN_MARKER).ok_or("No BDP encrypted message found.")? + BEGIN_MARKER.len();
+    let end = text[start..].find(END_MARKER).ok_or("Encrypted message is missing its END marker.")? + start;
+    let inner = text[start..end].trim().replace("\r\n", "\n");
+    let (headers, body) = inner.split_once("\n\n").ok_or("Encrypted message is missing its headers.")?;
+    let header = |name: &str| -> Result<&str, String> {
+        headers.lines()
+            .find_map(|line| line.strip_prefix(&format!("{}: ", name)))
+            .ok_or(format!("Encrypted message is missing the {} header.", name))
+    };
+    let cipher = match header("Cipher")? {
+        "AES-256-GCM" => Cipher::Aes256Gcm,
+        "ChaCha20-Poly1305" => Cipher::ChaCha20Poly1305,
+        other => return Err(format!("Unsupported cipher '{}'.", other)),
+    };
+    let kdf = parse_kdf(header("KDF")?)?;
+    let salt = STANDARD.decode(header("Salt")?).map_err(|e| format!("Invalid salt: {}", e))?;
+    let nonce = STANDARD.decode(header("Nonce")?).map_err(|e| format!("Invalid nonce: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("Invalid nonce: expected 12 bytes.".to_string());
+    }
+    let sealed = STANDARD.decode(body.split_whitespace().collect::<String>()).map_err(|e| format!("Invalid ciphertext: {}", e))?;
+    let key = derive_key(password, &salt, &kdf)?;
+    let plain = open(&cipher, &key, &nonce, &sealed, headers.as_bytes())?;
+    return String::from_utf8(plain).map_err(|e| format!("Decrypted data is not valid UTF-8: {}", e));
+}
+
+/// bcrypt hashes use the modular-crypt `$2b$` format; the others are PHC strings
+/// (`$argon2id$`, `$scrypt$`, `$pbkdf2-sha256$`) with each library's default parameters.
+pub fn hash_password(password: &str, alg: PasswordHashAlg) -> Result<String, String> {
+    if let PasswordHashAlg::Bcrypt = alg {
+        return bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string());
+    }
+    let mut bytes = [0u8; 16];
+    rand::fill(&mut bytes);
+    let salt = SaltString::encode_b64(&bytes).map_err(|e| e.to_string())?;
+    let hash = match alg {
+        PasswordHashAlg::Argon2id => Argon2::default().hash_password(password.as_bytes(), &salt),
+        PasswordHashAlg::Scrypt => scrypt::Scrypt.hash_password(password.as_bytes(), &salt),
+        PasswordHashAlg::Pbkdf2 => pbkdf2::Pbkdf2.hash_password(password.as_bytes(), &salt),
+        PasswordHashAlg::Bcrypt => unreachable!(),
+    };
+    return hash.map(|h| h.to_string()).map_err(|e| e.to_string());
+}
+
+/// Picks the algorithm from the hash itself. `Ok(false)` means the hash is
+/// well-formed but the password does not match it.
+pub fn verify_password(password: &str, hash: &str) -> Result<bool, String> {
+    let hash = hash.trim();
+    if hash.starts_with("$2") {
+        return bcrypt::verify(password, hash).map_err(|e| e.to_string());
+    }
+    let parsed = PasswordHash::new(hash).map_err(|e| format!("Unrecognised hash format: {}", e))?;
+    let result = match parsed.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => Argon2::default().verify_password(password.as_bytes(), &parsed),
+        "scrypt" => scrypt::Scrypt.verify_password(password.as_bytes(), &parsed),
+        "pbkdf2" | "pbkdf2-sha256" | "pbkdf2-sha512" => pbkdf2::Pbkdf2.verify_password(password.as_bytes(), &parsed),
+        other => return Err(format!("Unsupported hash algorithm '{}'.", other)),
+    };
+    return match result {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(e.to_string()),
+    };
+}
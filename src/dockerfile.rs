@@ -0,0 +1,280 @@
+use crate::layout::Layout;
+
+/// Instructions whose arguments are a shell command line, where `&&` and `||` are
+/// natural break points.
+const SHELL_INSTRUCTIONS: [&str; 3] = ["RUN", "CMD", "ENTRYPOINT"];
+
+/// The escape character set by a `# escape=` parser directive, which must come before
+/// any other comment or instruction.
+fn escape_char(lines: &[&str]) -> char {
+    for line in lines {
+        let line = line.trim();
+        let directive = match line.strip_prefix('#') {
+            Some(directive) => directive.trim(),
+            None => break,
+        };
+        match directive.split_once('=') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("escape") => {
+                return value.trim().chars().next().unwrap_or('\\');
+            },
+            Some((name, _)) if !name.trim().contains(' ') => {},
+            _ => break,
+        }
+    }
+    return '\\';
+}
+
+/// Splits on whitespace outside quotes, keeping quotes and escapes in the words.
+fn words(text: &str, escape: char) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        if escaped {
+            word.push(c);
+            escaped = false;
+            continue;
+        }
+        if c == escape && quote != Some('\'') {
+            word.push(c);
+            escaped = true;
+            continue;
+        }
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if !word.is_empty() {
+                    result.push(std::mem::take(&mut word));
+                }
+                continue;
+            },
+            None => {},
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        result.push(word);
+    }
+    return result;
+}
+
+/// Heredoc delimiters opened by an instruction, e.g. `EOF` for `RUN <<EOF` or
+/// `<<-"EOF"`, with whether leading tabs are stripped before matching.
+fn heredocs(instruction: &str) -> Vec<(String, bool)> {
+    let mut result = Vec::new();
+    let mut rest = instruction;
+    while let Some(pos) = rest.find("<<") {
+        rest = &rest[pos + 2..];
+        let strip = rest.starts_with('-');
+        let marker = rest.trim_start_matches('-');
+        let name: String = marker.trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            result.push((name, strip));
+        }
+    }
+    return result;
+}
+
+/// Exec form (`["a", "b"]`) is rewritten with normalised JSON spacing.
+fn exec_form(arguments: &str) -> Option<String> {
+    if !arguments.starts_with('[') {
+        return None;
+    }
+    let items: Vec<String> = serde_json::from_str(arguments).ok()?;
+    let items: Vec<String> = items.iter().map(|item| serde_json::to_string(item).unwrap_or_default()).collect();
+    return Some(format!("[{}]", items.join(", ")));
+}
+
+/// Keeps the instruction on one line when it fits the width. Otherwise it is continued
+/// over several lines, breaking before each `&&`/`||` in shell commands and wherever
+/// a word would overflow.
+fn wrap(keyword: &str, arguments: &str, layout: &Layout, escape: char) -> String {
+    if let Some(exec) = exec_form(arguments) {
+        return format!("{} {}", keyword, exec);
+    }
+    let words = words(arguments, escape);
+    let single = format!("{} {}", keyword, words.join(" "));
+    if layout.columns(0, &single) <= layout.width || words.is_empty() {
+        return single.trim_end().to_string();
+    }
+    let shell = SHELL_INSTRUCTIONS.contains(&keyword);
+    let mut lines = vec![keyword.to_string()];
+    let mut words_on_line = 0;
+    let mut after_operator = false;
+    for word in words {
+        let is_operator = shell && (word == "&&" || word == "||");
+        let line = lines.last().map_or("", |line| line.as_str());
+        let overflow = layout.columns(0, line) + word.chars().count() + 3 > layout.width;
+        if words_on_line > 0 && !after_operator && (is_operator || overflow) {
+            // Words wrapped inside an `&&` step sit one level deeper than the step.
+            let depth = if overflow && !is_operator && lines.len() > 1 && shell { 2 } else { 1 };
+            lines.push(format!("{}{}", layout.indent.repeat(depth), word));
+            words_on_line = 1;
+        }
+        else if let Some(line) = lines.last_mut() {
+            line.push(' ');
+            line.push_str(&word);
+            words_on_line += 1;
+        }
+        after_operator = is_operator;
+    }
+    return lines.join(&format!(" {}\n", escape));
+}
+
+/// Uppercases instruction keywords, joins continuation lines and re-wraps them to the
+/// width, normalises exec-form JSON, keeps heredoc bodies verbatim, squeezes blank
+/// lines and starts every build stage (`FROM`) after a blank line. Comments inside a
+/// continued instruction are moved above it.
+pub fn format(source: &str, layout: &Layout) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let escape = escape_char(&lines);
+    let mut output: Vec<String> = Vec::new();
+    let mut blank = false;
+    let mut seen_instruction = false;
+    // Where the comment lines directly above the next instruction start.
+    let mut comments_from: Option<usize> = None;
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx].trim();
+        idx += 1;
+        if line.is_empty() {
+            blank = !output.is_empty();
+            continue;
+        }
+        if line.starts_with('#') {
+            if blank {
+                output.push(String::new());
+            }
+            if blank || comments_from.is_none() {
+                comments_from = Some(output.len());
+            }
+            output.push(line.to_string());
+            blank = false;
+            continue;
+        }
+        let mut instruction = line.to_string();
+        let mut comments = Vec::new();
+        while instruction.ends_with(escape) && idx < lines.len() {
+            instruction.pop();
+            let next = lines[idx].trim();
+            idx += 1;
+            if next.starts_with('#') {
+                comments.push(next.to_string());
+                instruction.push(escape);
+            }
+            else if next.is_empty() {
+                instruction.push(escape);
+            }
+            else {
+                instruction.push(' ');
+                instruction.push_str(next);
+            }
+        }
+        let (keyword, arguments) = match instruction.split_once(char::is_whitespace) {
+            Some((keyword, arguments)) => (keyword.to_ascii_uppercase(), arguments.trim().to_string()),
+            None => (instruction.to_ascii_uppercase(), String::new()),
+        };
+        let (keyword, arguments) = match arguments.split_once(char::is_whitespace) {
+            Some((inner, rest)) if keyword == "ONBUILD" => (format!("ONBUILD {}", inner.to_ascii_uppercase()), rest.trim().to_string()),
+            _ => (keyword, arguments),
+        };
+        if blank {
+            output.push(String::new());
+        }
+        else if keyword == "FROM" && seen_instruction {
+            // The separating blank line goes above any comments describing the stage.
+            let at = comments_from.unwrap_or(output.len());
+            if at > 0 && !output[at - 1].is_empty() {
+                output.insert(at, String::new());
+            }
+        }
+        blank = false;
+        comments_from = None;
+        seen_instruction = true;
+        output.extend(comments);
+        let bodies = heredocs(&arguments);
+        if bodies.is_empty() {
+            output.push(wrap(&keyword, &arguments, layout, escape));
+            continue;
+        }
+        output.push(format!("{} {}", keyword, arguments));
+        for (delimiter, strip_tabs) in bodies {
+            while idx < lines.len() {
+                let body = lines[idx];
+                idx += 1;
+                output.push(body.to_string());
+                let end = if strip_tabs { body.trim_start_matches('\t') } else { body };
+                if end.trim_end() == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+    while output.last().is_some_and(|line| line.is_empty()) {
+        output.pop();
+    }
+    output.dedup_by(|a, b| a.is_empty() && b.is_empty());
+    return output.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_with_width(source: &str, width: usize) -> String {
+        return format(source, &Layout { indent: "  ".to_string(), width });
+    }
+
+    fn assert_idempotent(source: &str) {
+        let once = format_with_width(source, 80);
+        assert_eq!(format_with_width(&once, 80), once);
+    }
+
+    #[test]
+    fn keywords_stages_and_exec_form() {
+        let source = "from golang:1.22 as build\ncopy . .\ncmd [\"go\",\"run\",  \".\"]\n# runtime\nfrom alpine\nentrypoint [ \"/app\" ]\n";
+        let expected = "FROM golang:1.22 as build\nCOPY . .\nCMD [\"go\", \"run\", \".\"]\n\n# runtime\nFROM alpine\nENTRYPOINT [\"/app\"]";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn continuations_are_joined_and_rewrapped() {
+        let source = "run apt-get update && \\\n    # tools\n    apt-get install -y git curl ca-certificates build-essential && rm -rf /var/lib/apt/lists/*\nrun echo \\\n  short\n";
+        let expected = "# tools\nRUN apt-get update \\\n  && apt-get install -y git curl ca-certificates build-essential \\\n  && rm -rf /var/lib/apt/lists/*\nRUN echo short";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn long_words_wrap_at_width() {
+        let source = "ENV A=aaaaaaaaaa B=bbbbbbbbbb C=cccccccccc D=dddddddddd";
+        assert_eq!(format_with_width(source, 80), source);
+        let expected = "ENV A=aaaaaaaaaa \\\n  B=bbbbbbbbbb C=cccccccccc \\\n  D=dddddddddd";
+        assert_eq!(format_with_width(source, 30), expected);
+        assert!(expected.lines().all(|line| line.len() <= 30));
+        assert_eq!(format_with_width(expected, 30), expected);
+    }
+
+    #[test]
+    fn escape_directive() {
+        let source = "# escape=`\nfrom mcr.microsoft.com/windows\nrun dir c:\\ && `\n    echo done\n";
+        let expected = "# escape=`\nFROM mcr.microsoft.com/windows\nRUN dir c:\\ && echo done";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn heredoc_bodies_are_kept() {
+        let source = "from alpine\nrun <<EOF\necho \"keep   this\"\n  indented\nEOF\ncopy <<-\"END\" /etc/conf\n\tkey = value\n\tEND\nrun   true\n";
+        let expected = "FROM alpine\nRUN <<EOF\necho \"keep   this\"\n  indented\nEOF\nCOPY <<-\"END\" /etc/conf\n\tkey = value\n\tEND\nRUN true";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+}
@@ -1,18 +1,35 @@
-use std::collections::HashMap;
+use std::fs;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use sha1::{Sha1, Digest};
 use sha2::Sha256;
 use sha2::Sha512;
+use url::form_urlencoded;
+use crate::{ciphers, crypto, jwt, magic, utils};
 use percent_encoding::{utf8_percent_encode, percent_decode_str, AsciiSet, NON_ALPHANUMERIC, CONTROLS};
 
 pub enum EncodingOp {
-    Rot13, Base64Encode, Base64Decode, Md5, Sha1, Sha256, Sha512, HtmlEncode, HtmlDecode,
-    HtmlEncodeAll, UrlEncode, UrlDecode, UrlEntityEncode, UrlEntitiesDecode, Unknown,
+    Rot13, Base64Encode, Base64Decode, Md5, Sha1, Sha256, Sha512, HtmlEncode, HtmlEncodeMinimal,
+    HtmlEncodeNonAscii, HtmlDecode, HtmlEncodeAll, UrlEncode, UrlEncodePath, UrlEncodeQuery,
+    UrlEncodeForm, UrlEncodeFull, UrlDecode, UrlDecodeForm, UrlEntityEncode, UrlEntitiesDecode, JwtDecode, Encrypt, Decrypt, BcryptHash,
+    ScryptHash, Argon2Hash, Pbkdf2Hash, VerifyPassword, RotN, Rot47, Atbash, VigenereEncode,
+    VigenereDecode, XorEncode, XorDecode, MorseEncode, MorseDecode, Nato, Magic, Unknown,
 }
 
 fn str_to_encoding_op(arg: &str) -> EncodingOp {
     return match arg {
         "rot13" => EncodingOp::Rot13,
+        "rot_n" => EncodingOp::RotN,
+        "rot47" => EncodingOp::Rot47,
+        "atbash" => EncodingOp::Atbash,
+        "vigenere_encode" => EncodingOp::VigenereEncode,
+        "vigenere_decode" => EncodingOp::VigenereDecode,
+        "xor_encode" => EncodingOp::XorEncode,
+        "xor_decode" => EncodingOp::XorDecode,
+        "morse_encode" => EncodingOp::MorseEncode,
+        "morse_decode" => EncodingOp::MorseDecode,
+        "nato" => EncodingOp::Nato,
+        "magic" => EncodingOp::Magic,
+        "identify" => EncodingOp::Magic,
         "base64_encode" => EncodingOp::Base64Encode,
         "base64_decode" => EncodingOp::Base64Decode,
         "md5" => EncodingOp::Md5,
@@ -20,18 +37,34 @@ fn str_to_encoding_op(arg: &str) -> EncodingOp {
         "sha256" => EncodingOp::Sha256,
         "sha512" => EncodingOp::Sha512,
         "html_encode" => EncodingOp::HtmlEncode,
+        "html_encode_attribute" => EncodingOp::HtmlEncode,
+        "html_encode_minimal" => EncodingOp::HtmlEncodeMinimal,
+        "html_encode_non_ascii" => EncodingOp::HtmlEncodeNonAscii,
         "html_decode" => EncodingOp::HtmlDecode,
         "html_encode_all" => EncodingOp::HtmlEncodeAll,
         "url_encode" => EncodingOp::UrlEncode, 
+        "url_encode_path" => EncodingOp::UrlEncodePath,
+        "url_encode_query" => EncodingOp::UrlEncodeQuery,
+        "url_encode_form" => EncodingOp::UrlEncodeForm,
+        "url_encode_full" => EncodingOp::UrlEncodeFull,
         "url_decode" => EncodingOp::UrlDecode, 
+        "url_decode_form" => EncodingOp::UrlDecodeForm,
         "url_entity_encode" => EncodingOp::UrlEntityEncode, 
         "url_entities_decode" => EncodingOp::UrlEntitiesDecode, 
+        "jwt_decode" => EncodingOp::JwtDecode,
+        "encrypt" => EncodingOp::Encrypt,
+        "decrypt" => EncodingOp::Decrypt,
+        "bcrypt_hash" => EncodingOp::BcryptHash,
+        "scrypt_hash" => EncodingOp::ScryptHash,
+        "argon2_hash" => EncodingOp::Argon2Hash,
+        "pbkdf2_hash" => EncodingOp::Pbkdf2Hash,
+        "verify_password" => EncodingOp::VerifyPassword,
         _ => EncodingOp::Unknown,
     }
 }
 
 pub fn select_encoding_option(args: &Vec<String>) -> EncodingOp {
-    let encoding_options: Vec<String> = vec!["rot13", "base64_encode", "base64_decode", "md5", "html_encode", "html_decode", "html_encode_all", "url_encode", "url_decode", "url_entity_encode", "url_entities_decode", "sha1", "sha256", "sha512"].into_iter().map(|x| x.to_string()).collect();
+    let encoding_options: Vec<String> = vec!["rot13", "rot_n", "rot47", "atbash", "vigenere_encode", "vigenere_decode", "xor_encode", "xor_decode", "morse_encode", "morse_decode", "nato", "magic", "identify", "base64_encode", "base64_decode", "md5", "html_encode", "html_encode_attribute", "html_encode_minimal", "html_encode_non_ascii", "html_decode", "html_encode_all", "url_encode", "url_encode_path", "url_encode_query", "url_encode_form", "url_encode_full", "url_decode", "url_decode_form", "url_entity_encode", "url_entities_decode", "jwt_decode", "encrypt", "decrypt", "sha1", "sha256", "sha512", "bcrypt_hash", "scrypt_hash", "argon2_hash", "pbkdf2_hash", "verify_password"].into_iter().map(|x| x.to_string()).collect();
     for arg in args {
         if encoding_options.contains(&arg.to_lowercase()) {
             return str_to_encoding_op(arg);
@@ -40,45 +73,143 @@ pub fn select_encoding_option(args: &Vec<String>) -> EncodingOp {
     return EncodingOp::Unknown
 }
 
-pub fn handle_encoding_operation(text: &str, op: EncodingOp) -> String {
+pub fn handle_encoding_operation(text: &str, op: EncodingOp, args: &Vec<String>) -> String {
     return match op {
-        EncodingOp::Rot13 => rot13(text),
+        EncodingOp::Rot13 => ciphers::rot_n(text, 13),
+        EncodingOp::RotN => ciphers::rot_n(text, get_shift(args)),
+        EncodingOp::Rot47 => ciphers::rot47(text),
+        EncodingOp::Atbash => ciphers::atbash(text),
+        EncodingOp::VigenereEncode => ciphers::vigenere(text, &get_cipher_key(args), false),
+        EncodingOp::VigenereDecode => ciphers::vigenere(text, &get_cipher_key(args), true),
+        EncodingOp::XorEncode => ciphers::xor_encode(text, &get_cipher_key(args)),
+        EncodingOp::XorDecode => ciphers::xor_decode(text, &get_cipher_key(args)),
+        EncodingOp::MorseEncode => ciphers::morse_encode(text),
+        EncodingOp::MorseDecode => ciphers::morse_decode(text),
+        EncodingOp::Nato => ciphers::nato(text),
+        EncodingOp::Magic => magic::magic(text),
         EncodingOp::Base64Encode => base64_encode(text),
         EncodingOp::Base64Decode => base64_decode(text),
         EncodingOp::Md5 => md5(text),
         EncodingOp::Sha1 => sha1(text),
         EncodingOp::Sha256 => sha256(text),
         EncodingOp::Sha512 => sha512(text),
-        EncodingOp::HtmlEncode => html_encode(text),
+        EncodingOp::HtmlEncode => html_encode(text, HtmlEncodeMode::Attribute),
+        EncodingOp::HtmlEncodeMinimal => html_encode(text, HtmlEncodeMode::Minimal),
+        EncodingOp::HtmlEncodeNonAscii => html_encode(text, HtmlEncodeMode::NonAscii),
         EncodingOp::HtmlDecode => html_decode(text),
-        EncodingOp::HtmlEncodeAll => html_encode_all(text),
-        EncodingOp::UrlEncode => url_encode(text),
-        EncodingOp::UrlDecode => url_decode(text),
+        EncodingOp::HtmlEncodeAll => html_encode(text, HtmlEncodeMode::All),
+        EncodingOp::UrlEncode => url_encode(text, UrlEncodeMode::Unreserved),
+        EncodingOp::UrlEncodePath => url_encode(text, UrlEncodeMode::PathSegment),
+        EncodingOp::UrlEncodeQuery => url_encode(text, UrlEncodeMode::QueryComponent),
+        EncodingOp::UrlEncodeForm => url_encode(text, UrlEncodeMode::Form),
+        EncodingOp::UrlEncodeFull => url_encode(text, UrlEncodeMode::FullUrl),
+        EncodingOp::UrlDecode => url_decode(text, false),
+        EncodingOp::UrlDecodeForm => url_decode(text, true),
         EncodingOp::UrlEntityEncode => url_entity_encode(text),
         EncodingOp::UrlEntitiesDecode=> url_entities_decode(text),
+        EncodingOp::JwtDecode => jwt::jwt_decode(text, select_jwt_key(args)),
+        EncodingOp::Encrypt => encrypt(text, args),
+        EncodingOp::Decrypt => decrypt(text, args),
+        EncodingOp::BcryptHash => hash_password(text, crypto::PasswordHashAlg::Bcrypt),
+        EncodingOp::ScryptHash => hash_password(text, crypto::PasswordHashAlg::Scrypt),
+        EncodingOp::Argon2Hash => hash_password(text, crypto::PasswordHashAlg::Argon2id),
+        EncodingOp::Pbkdf2Hash => hash_password(text, crypto::PasswordHashAlg::Pbkdf2),
+        EncodingOp::VerifyPassword => verify_password(text, args),
         EncodingOp::Unknown => panic!("Unknown encoding operation specified."),
     };
 }
 
-fn rot13(text: &str) -> String {
-    return {
-        text.chars()
-            .map(|c| match c {
-                'A'..='Z' => (((c as u8 - b'A' + 13) % 26) + b'A') as char,
-                'a'..='z' => (((c as u8 - b'a' + 13) % 26) + b'a') as char,
-                _ => c,
-            })
-            .collect()
+/// `--secret` verifies HMAC tokens; `--key` names a PEM public key file for RS256/ES256.
+fn select_jwt_key(args: &Vec<String>) -> jwt::JwtKey {
+    if let Some(secret) = utils::get_option_value(args, "secret") {
+        return jwt::JwtKey::Secret(secret);
+    }
+    if let Some(path) = utils::get_option_value(args, "key") {
+        return match fs::read_to_string(&path) {
+            Ok(pem) => jwt::JwtKey::Pem(pem),
+            Err(e) => panic!("Unable to read key file {}: {}", path, e),
+        };
+    }
+    return jwt::JwtKey::None;
+}
+
+/// `--password-file=<path>` (its first line) or the `BDP_PASSWORD` environment variable
+/// keep the password out of `ps` and shell history; `--password=<password>` is kept for
+/// quick use but is visible to other users of the machine.
+fn get_password(args: &Vec<String>) -> String {
+    if let Some(path) = utils::get_option_value(args, "password-file") {
+        return match fs::read_to_string(&path) {
+            Ok(text) if !text.lines().next().unwrap_or("").is_empty() => text.lines().next().unwrap_or("").to_string(),
+            Ok(_) => panic!("Password file {} is empty.", path),
+            Err(e) => panic!("Unable to read password file {}: {}", path, e),
+        };
+    }
+    if let Ok(password) = std::env::var("BDP_PASSWORD") {
+        return password;
+    }
+    return utils::get_option_value(args, "password")
+        .expect("A password is required (--password-file=<path>, BDP_PASSWORD or --password=<password>).");
+}
+
+/// `--cipher` picks aes-256-gcm (default) or chacha20-poly1305; `--kdf` picks argon2 (default) or pbkdf2.
+fn encrypt(text: &str, args: &Vec<String>) -> String {
+    let cipher = utils::get_option_value(args, "cipher").map(|c| crypto::str_to_cipher(&c)).unwrap_or(crypto::Cipher::Aes256Gcm);
+    let kdf = crypto::str_to_kdf(&utils::get_option_value(args, "kdf").unwrap_or("argon2".to_string()));
+    return match crypto::encrypt(text, &get_password(args), cipher, kdf) {
+        Ok(result) => result,
+        Err(e) => panic!("Unable to encrypt: {}", e),
+    };
+}
+
+/// The cipher and KDF are read back from the message headers.
+fn decrypt(text: &str, args: &Vec<String>) -> String {
+    return match crypto::decrypt(text, &get_password(args)) {
+        Ok(result) => result,
+        Err(e) => panic!("Unable to decrypt: {}", e),
+    };
+}
+
+/// A trailing newline is never part of a password, so it is dropped before hashing.
+fn strip_newline(text: &str) -> &str {
+    return text.strip_suffix('\n').map(|t| t.strip_suffix('\r').unwrap_or(t)).unwrap_or(text);
+}
+
+fn hash_password(text: &str, alg: crypto::PasswordHashAlg) -> String {
+    return match crypto::hash_password(strip_newline(text), alg) {
+        Ok(result) => result,
+        Err(e) => panic!("Unable to hash password: {}", e),
     };
 }
 
+/// Checks the input password against `--hash=<hash>`.
+fn verify_password(text: &str, args: &Vec<String>) -> String {
+    let hash = utils::get_option_value(args, "hash").expect("A hash to verify against is required (--hash=<hash>).");
+    return match crypto::verify_password(strip_newline(text), &hash) {
+        Ok(true) => "Password matches.".to_string(),
+        Ok(false) => "Password does NOT match.".to_string(),
+        Err(e) => panic!("Unable to verify password: {}", e),
+    };
+}
+
+/// `--shift=<n>` for rot_n, defaulting to 13; negative shifts decode.
+fn get_shift(args: &Vec<String>) -> i32 {
+    return match utils::get_option_value(args, "shift") {
+        Some(value) => value.parse().unwrap_or_else(|_| panic!("--shift expects a whole number, got '{}'.", value)),
+        None => 13,
+    };
+}
+
+fn get_cipher_key(args: &Vec<String>) -> String {
+    return utils::get_option_value(args, "key").expect("This cipher requires a key (--key=<key>).");
+}
+
 fn base64_encode(text: &str) -> String {
     return STANDARD.encode(text);
 }
 
 fn base64_decode(text: &str) -> String {
     let res = STANDARD.decode(text).unwrap_or_default();
-    if res.len() == 0 { return text.to_string(); }
+    if res.is_empty() { return text.to_string(); }
     let result = String::from_utf8(res).unwrap_or(text.to_string());
     return result;
 }
@@ -108,66 +239,84 @@ fn sha512(text: &str) -> String {
     return format!("{:x}", result);
 }
 
-fn html_encode(text: &str) -> String {
-    let codes = HashMap::from([
-        ('<', "&#x3C;"),
-        ('>', "&#x3E;"),
-        ('&', "&#x26;"),
-        ('\'', "&#x22;"),
-        ('"', "&#x27;"),
-    ]);
+enum HtmlEncodeMode {
+    Minimal, Attribute, NonAscii, All,
+}
+
+fn html_escape_char(c: char) -> Option<&'static str> {
+    return match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '"' => Some("&quot;"),
+        '\'' => Some("&#x27;"),
+        _ => None,
+    };
+}
+
+/// Minimal only escapes what would otherwise be read as markup (`&`, `<`, `>`),
+/// Attribute also escapes both quote characters, NonAscii additionally writes
+/// every non-ASCII character as a hex reference, and All encodes everything.
+fn html_encode(text: &str, mode: HtmlEncodeMode) -> String {
     return text.chars().map(|c| {
-        if let Some(code) = codes.get(&c) { code.to_string() } 
+        let escaped = match mode {
+            HtmlEncodeMode::Minimal if c == '"' || c == '\'' => None,
+            HtmlEncodeMode::All => return format!("&#{};", c as u32),
+            _ => html_escape_char(c),
+        };
+        if let Some(code) = escaped { code.to_string() }
+        else if matches!(mode, HtmlEncodeMode::NonAscii) && !c.is_ascii() { format!("&#x{:X};", c as u32) }
         else { c.to_string() }
     }).collect();
 }
 
+/// Decodes every WHATWG named entity (including the legacy forms without a
+/// trailing semicolon) as well as decimal and hex numeric references.
 fn html_decode(text: &str) -> String {
-    let codes = HashMap::from([
-        ("&#x3C;", '<'),
-        ("&#x3E;", '>'),
-        ("&#x26;", '&'),
-        ("&#x22;", '\''),
-        ("&#x27;", '"'),
-    ]);
-    let mut result = String::new();
-    let mut i = 0;
-    let chars: Vec<char> = text.chars().collect();
-    while i < chars.len() {
-        if chars[i] == '&' {
-            if let Some(semi_idx) = (i + 1..chars.len()).find(|&j| chars[j] == ';') {
-                let entity: String = chars[i..=semi_idx].iter().collect();
-                if let Some(decoded_char) = codes.get(entity.as_str()) {
-                    result.push(*decoded_char);
-                    i = semi_idx + 1;
-                    continue;
-                }
-            }
-        }
-        result.push(chars[i]);
-        i += 1;
-    }
-    return result;
+    return htmlize::unescape(text).into_owned();
 }
 
-fn html_encode_all(text: &str) -> String {
-    return {
-        text
-            .chars()
-            .map(|c| format!("&#{};", c as u32))
-            .collect()
-    };
+enum UrlEncodeMode {
+    Unreserved, PathSegment, QueryComponent, Form, FullUrl,
 }
 
-fn url_encode(text: &str) -> String {
-    utf8_percent_encode(text, NON_ALPHANUMERIC).to_string()
-}
+/// RFC 3986 unreserved characters (`A-Z a-z 0-9 - . _ ~`) are the only ones left alone.
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// A single path segment may keep sub-delims, `:` and `@`, but not `/`, `?` or `#`.
+const PATH_SEGMENT: &AsciiSet = &UNRESERVED
+    .remove(b'!').remove(b'$').remove(b'&').remove(b'\'').remove(b'(').remove(b')')
+    .remove(b'*').remove(b'+').remove(b',').remove(b';').remove(b'=').remove(b':')
+    .remove(b'@');
 
+/// A query key or value must escape the separators `&`, `=`, `+` and `#`.
+const QUERY_COMPONENT: &AsciiSet = &UNRESERVED
+    .remove(b'!').remove(b'$').remove(b'\'').remove(b'(').remove(b')').remove(b'*')
+    .remove(b',').remove(b';').remove(b':').remove(b'@').remove(b'/').remove(b'?');
 
-fn url_decode(text: &str) -> String {
-    percent_decode_str(text).decode_utf8_lossy().to_string()
+/// Same as JavaScript's `encodeURI`: every reserved character but `[` and `]` is preserved.
+const FULL_URL: &AsciiSet = &PATH_SEGMENT
+    .remove(b'/').remove(b'?').remove(b'#');
+
+fn url_encode(text: &str, mode: UrlEncodeMode) -> String {
+    let set = match mode {
+        UrlEncodeMode::Unreserved => UNRESERVED,
+        UrlEncodeMode::PathSegment => PATH_SEGMENT,
+        UrlEncodeMode::QueryComponent => QUERY_COMPONENT,
+        UrlEncodeMode::FullUrl => FULL_URL,
+        UrlEncodeMode::Form => return form_urlencoded::byte_serialize(text.as_bytes()).collect(),
+    };
+    return utf8_percent_encode(text, set).to_string();
 }
 
+/// With `plus_as_space` the text is treated as `application/x-www-form-urlencoded`.
+fn url_decode(text: &str, plus_as_space: bool) -> String {
+    if plus_as_space {
+        return percent_decode_str(&text.replace('+', " ")).decode_utf8_lossy().to_string();
+    }
+    return percent_decode_str(text).decode_utf8_lossy().to_string();
+}
 
 /// Define a custom set that matches nothing = encode everything
 const ENCODE_ALL: &AsciiSet = &CONTROLS
@@ -193,6 +342,6 @@ fn url_entity_encode(text: &str) -> String {
 }
 
 fn url_entities_decode(text: &str) -> String {
-    url_decode(text)  
+    url_decode(text, false)
 }
 
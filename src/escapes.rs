@@ -0,0 +1,531 @@
+use crate::utils;
+
+pub enum EscapeOp {
+    EscapeUnicode, UnescapeUnicode, EscapeUnicodeBraced, UnescapeUnicodeBraced, EscapeHex,
+    UnescapeHex, EscapeOctal, UnescapeOctal, EscapeUnicodeName, UnescapeUnicodeName, EscapeJson,
+    UnescapeJson, EscapeC, UnescapeC, EscapePython, UnescapePython, EscapeShell, UnescapeShell,
+    EscapeSql, UnescapeSql, EscapeRegex, UnescapeRegex, EscapeCsv, UnescapeCsv, EscapeXmlAttr,
+    UnescapeXmlAttr, Unknown,
+}
+
+fn str_to_escape_op(arg: &str) -> EscapeOp {
+    return match arg {
+        "escape_unicode" => EscapeOp::EscapeUnicode,
+        "unescape_unicode" => EscapeOp::UnescapeUnicode,
+        "escape_unicode_braced" => EscapeOp::EscapeUnicodeBraced,
+        "unescape_unicode_braced" => EscapeOp::UnescapeUnicodeBraced,
+        "escape_hex" => EscapeOp::EscapeHex,
+        "unescape_hex" => EscapeOp::UnescapeHex,
+        "escape_octal" => EscapeOp::EscapeOctal,
+        "unescape_octal" => EscapeOp::UnescapeOctal,
+        "escape_unicode_name" => EscapeOp::EscapeUnicodeName,
+        "unescape_unicode_name" => EscapeOp::UnescapeUnicodeName,
+        "escape_json" => EscapeOp::EscapeJson,
+        "unescape_json" => EscapeOp::UnescapeJson,
+        "escape_c" => EscapeOp::EscapeC,
+        "escape_java" => EscapeOp::EscapeC,
+        "unescape_c" => EscapeOp::UnescapeC,
+        "unescape_java" => EscapeOp::UnescapeC,
+        "escape_python" => EscapeOp::EscapePython,
+        "unescape_python" => EscapeOp::UnescapePython,
+        "escape_shell" => EscapeOp::EscapeShell,
+        "unescape_shell" => EscapeOp::UnescapeShell,
+        "escape_sql" => EscapeOp::EscapeSql,
+        "unescape_sql" => EscapeOp::UnescapeSql,
+        "escape_regex" => EscapeOp::EscapeRegex,
+        "unescape_regex" => EscapeOp::UnescapeRegex,
+        "escape_csv" => EscapeOp::EscapeCsv,
+        "unescape_csv" => EscapeOp::UnescapeCsv,
+        "escape_xml_attr" => EscapeOp::EscapeXmlAttr,
+        "unescape_xml_attr" => EscapeOp::UnescapeXmlAttr,
+        _ => EscapeOp::Unknown,
+    }
+}
+
+pub fn select_escape_option(args: &Vec<String>) -> EscapeOp {
+    let escape_options: Vec<String> = vec!["escape_unicode", "unescape_unicode", "escape_unicode_braced", "unescape_unicode_braced", "escape_hex", "unescape_hex", "escape_octal", "unescape_octal", "escape_unicode_name", "unescape_unicode_name", "escape_json", "unescape_json", "escape_c", "unescape_c", "escape_java", "unescape_java", "escape_python", "unescape_python", "escape_shell", "unescape_shell", "escape_sql", "unescape_sql", "escape_regex", "unescape_regex", "escape_csv", "unescape_csv", "escape_xml_attr", "unescape_xml_attr"].into_iter().map(|x| x.to_string()).collect();
+    for arg in args {
+        if escape_options.contains(&arg.to_lowercase()) {
+            return str_to_escape_op(arg);
+        }
+    }
+    return EscapeOp::Unknown
+}
+
+/// By default only non-ASCII and control characters (other than `\n`, `\r` and `\t`)
+/// are escaped; `--all` escapes every character.
+pub fn handle_escape_operation(text: &str, op: EscapeOp, args: &Vec<String>) -> String {
+    let all = utils::has_option(args, "all");
+    return match op {
+        EscapeOp::EscapeUnicode => escape_unicode(text, all),
+        EscapeOp::UnescapeUnicode => unescape_unicode(text),
+        EscapeOp::EscapeUnicodeBraced => escape_unicode_braced(text, all),
+        EscapeOp::UnescapeUnicodeBraced => unescape_unicode_braced(text),
+        EscapeOp::EscapeHex => escape_hex(text, all),
+        EscapeOp::UnescapeHex => unescape_hex(text),
+        EscapeOp::EscapeOctal => escape_octal(text, all),
+        EscapeOp::UnescapeOctal => unescape_octal(text),
+        EscapeOp::EscapeUnicodeName => escape_unicode_name(text, all),
+        EscapeOp::UnescapeUnicodeName => unescape_unicode_name(text),
+        EscapeOp::EscapeJson => escape_json(text),
+        EscapeOp::UnescapeJson => unescape_json(text),
+        EscapeOp::EscapeC => escape_c(text),
+        EscapeOp::UnescapeC => unescape_c(text),
+        EscapeOp::EscapePython => escape_python(text),
+        EscapeOp::UnescapePython => unescape_python(text),
+        EscapeOp::EscapeShell => escape_shell(text),
+        EscapeOp::UnescapeShell => unescape_shell(text),
+        EscapeOp::EscapeSql => escape_sql(text),
+        EscapeOp::UnescapeSql => unescape_sql(text),
+        EscapeOp::EscapeRegex => regex::escape(text),
+        EscapeOp::UnescapeRegex => unescape_regex(text),
+        EscapeOp::EscapeCsv => escape_csv(text),
+        EscapeOp::UnescapeCsv => unescape_csv(text),
+        EscapeOp::EscapeXmlAttr => escape_xml_attr(text),
+        EscapeOp::UnescapeXmlAttr => unescape_xml_attr(text),
+        EscapeOp::Unknown => panic!("Unknown escape operation specified."),
+    };
+}
+
+fn needs_escape(c: char, all: bool) -> bool {
+    return all || !c.is_ascii() || (c.is_ascii_control() && !matches!(c, '\n' | '\r' | '\t'));
+}
+
+/// A backslash left as it is would be read back as the start of a sequence, so the
+/// codecs write it as `\\` unless every character is being escaped anyway.
+fn escape_backslash(c: char, all: bool) -> Option<String> {
+    return if c == '\\' && !all { Some("\\\\".to_string()) } else { None };
+}
+
+/// Walks the text handing every backslash to `decode`, which returns the bytes to
+/// emit and how many chars it consumed. `\\` is an escaped backslash, never the start
+/// of a sequence, so `\\x41` decodes to `\x41`. Working in bytes lets `\xNN` and
+/// octal sequences rebuild multi-byte UTF-8 characters.
+fn unescape_with<F: Fn(&[char], usize) -> Option<(Vec<u8>, usize)>>(text: &str, decode: F) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            if chars.get(i + 1) == Some(&'\\') {
+                result.push(b'\\');
+                i += 2;
+                continue;
+            }
+            if let Some((bytes, consumed)) = decode(&chars, i) {
+                result.extend(bytes);
+                i += consumed;
+                continue;
+            }
+        }
+        let mut buf = [0u8; 4];
+        result.extend_from_slice(chars[i].encode_utf8(&mut buf).as_bytes());
+        i += 1;
+    }
+    return String::from_utf8_lossy(&result).to_string();
+}
+
+fn char_bytes(c: char) -> Vec<u8> {
+    return c.to_string().into_bytes();
+}
+
+/// Reads exactly `len` hex digits starting at `start`.
+fn read_hex(chars: &[char], start: usize, len: usize) -> Option<u32> {
+    let digits: String = chars.get(start..start + len)?.iter().collect();
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) { return None; }
+    return u32::from_str_radix(&digits, 16).ok();
+}
+
+/// Characters outside the Basic Multilingual Plane become a UTF-16 surrogate pair,
+/// as JSON, Java and JavaScript expect.
+pub fn escape_unicode(text: &str, all: bool) -> String {
+    return text.chars().map(|c| {
+        if let Some(escaped) = escape_backslash(c, all) { return escaped; }
+        if !needs_escape(c, all) { return c.to_string(); }
+        let mut units = [0u16; 2];
+        c.encode_utf16(&mut units).iter().map(|u| format!("\\u{:04X}", u)).collect()
+    }).collect();
+}
+
+/// Combines surrogate pairs; a lone surrogate is left as written.
+pub fn unescape_unicode(text: &str) -> String {
+    return unescape_with(text, |chars, i| {
+        if chars.get(i + 1) != Some(&'u') { return None; }
+        let unit = read_hex(chars, i + 2, 4)?;
+        if (0xD800..0xDC00).contains(&unit) {
+            if chars.get(i + 6) != Some(&'\\') || chars.get(i + 7) != Some(&'u') { return None; }
+            let low = read_hex(chars, i + 8, 4)?;
+            if !(0xDC00..0xE000).contains(&low) { return None; }
+            let combined = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+            return Some((char_bytes(char::from_u32(combined)?), 12));
+        }
+        return Some((char_bytes(char::from_u32(unit)?), 6));
+    });
+}
+
+pub fn escape_unicode_braced(text: &str, all: bool) -> String {
+    return text.chars().map(|c| {
+        if let Some(escaped) = escape_backslash(c, all) { escaped }
+        else if needs_escape(c, all) { format!("\\u{{{:X}}}", c as u32) }
+        else { c.to_string() }
+    }).collect();
+}
+
+pub fn unescape_unicode_braced(text: &str) -> String {
+    return unescape_with(text, |chars, i| {
+        if chars.get(i + 1) != Some(&'u') || chars.get(i + 2) != Some(&'{') { return None; }
+        let close = (i + 3..chars.len().min(i + 10)).find(|&j| chars[j] == '}')?;
+        let value = read_hex(chars, i + 3, close - i - 3)?;
+        return Some((char_bytes(char::from_u32(value)?), close - i + 1));
+    });
+}
+
+/// Escapes the UTF-8 bytes of each character, so `é` becomes `\xc3\xa9`.
+pub fn escape_hex(text: &str, all: bool) -> String {
+    return text.chars().map(|c| {
+        if let Some(escaped) = escape_backslash(c, all) { escaped }
+        else if needs_escape(c, all) { char_bytes(c).iter().map(|b| format!("\\x{:02x}", b)).collect() }
+        else { c.to_string() }
+    }).collect();
+}
+
+pub fn unescape_hex(text: &str) -> String {
+    return unescape_with(text, |chars, i| {
+        if chars.get(i + 1) != Some(&'x') { return None; }
+        let value = read_hex(chars, i + 2, 2)?;
+        return Some((vec![value as u8], 4));
+    });
+}
+
+/// Like `escape_hex`, but each byte is written as a three-digit octal sequence.
+pub fn escape_octal(text: &str, all: bool) -> String {
+    return text.chars().map(|c| {
+        if let Some(escaped) = escape_backslash(c, all) { escaped }
+        else if needs_escape(c, all) { char_bytes(c).iter().map(|b| format!("\\{:03o}", b)).collect() }
+        else { c.to_string() }
+    }).collect();
+}
+
+/// Accepts one to three octal digits, up to `\377`.
+pub fn unescape_octal(text: &str) -> String {
+    return unescape_with(text, |chars, i| {
+        let digits: String = chars[i + 1..].iter().take(3).take_while(|c| ('0'..='7').contains(*c)).collect();
+        if digits.is_empty() { return None; }
+        let value = u32::from_str_radix(&digits, 8).ok()?;
+        if value > 0xFF { return None; }
+        return Some((vec![value as u8], digits.len() + 1));
+    });
+}
+
+/// Python's `\N{NAME}` form. Characters without a Unicode name fall back to `\u{...}`.
+pub fn escape_unicode_name(text: &str, all: bool) -> String {
+    return text.chars().map(|c| {
+        if let Some(escaped) = escape_backslash(c, all) { return escaped; }
+        if !needs_escape(c, all) { return c.to_string(); }
+        match unicode_names2::name(c) {
+            Some(name) => format!("\\N{{{}}}", name),
+            None => format!("\\u{{{:X}}}", c as u32),
+        }
+    }).collect();
+}
+
+/// Also reads the `\u{...}` fallback that `escape_unicode_name` writes for unnamed characters.
+pub fn unescape_unicode_name(text: &str) -> String {
+    return unescape_with(text, |chars, i| {
+        let kind = *chars.get(i + 1)?;
+        if (kind != 'N' && kind != 'u') || chars.get(i + 2) != Some(&'{') { return None; }
+        let close = (i + 3..chars.len()).find(|&j| chars[j] == '}')?;
+        let name: String = chars[i + 3..close].iter().collect();
+        let c = match kind {
+            'N' => unicode_names2::character(&name)?,
+            _ => char::from_u32(u32::from_str_radix(&name, 16).ok()?)?,
+        };
+        return Some((char_bytes(c), close - i + 1));
+    });
+}
+
+/// The contents of a JSON string, without the surrounding quotes.
+fn escape_json(text: &str) -> String {
+    let quoted = serde_json::to_string(text).unwrap();
+    return quoted[1..quoted.len() - 1].to_string();
+}
+
+fn unescape_json(text: &str) -> String {
+    let trimmed = text.trim_end_matches(['\n', '\r']);
+    let inner = trimmed.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(trimmed);
+    return match serde_json::from_str::<String>(&format!("\"{}\"", inner)) {
+        Ok(result) => result,
+        Err(e) => panic!("Unable to unescape JSON string: {}", e),
+    };
+}
+
+/// The contents of a double-quoted C or Java string. Control characters without a
+/// short escape are written as three-digit octal, which both languages accept.
+fn escape_c(text: &str) -> String {
+    return text.chars().map(|c| match c {
+        '\\' => "\\\\".to_string(),
+        '"' => "\\\"".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        '\u{08}' => "\\b".to_string(),
+        '\u{0C}' => "\\f".to_string(),
+        c if c.is_ascii_control() => format!("\\{:03o}", c as u32),
+        c => c.to_string(),
+    }).collect();
+}
+
+/// Handles the escapes shared by C, Java and Python string literals. `python`
+/// additionally enables `\N{NAME}` and keeps unknown escapes verbatim, as Python does.
+fn unescape_common(text: &str, python: bool) -> String {
+    return unescape_with(text, |chars, i| {
+        let next = *chars.get(i + 1)?;
+        let simple = match next {
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'a' => Some('\u{07}'),
+            'b' => Some('\u{08}'),
+            'f' => Some('\u{0C}'),
+            'v' => Some('\u{0B}'),
+            '"' | '\'' | '?' => Some(next),
+            _ => None,
+        };
+        if let Some(c) = simple {
+            return Some((char_bytes(c), 2));
+        }
+        return match next {
+            '0'..='7' => {
+                let digits: String = chars[i + 1..].iter().take(3).take_while(|c| ('0'..='7').contains(*c)).collect();
+                let value = u32::from_str_radix(&digits, 8).ok()?;
+                Some((char_bytes(char::from_u32(value)?), digits.len() + 1))
+            },
+            'x' => Some((char_bytes(char::from_u32(read_hex(chars, i + 2, 2)?)?), 4)),
+            'u' => Some((char_bytes(char::from_u32(read_hex(chars, i + 2, 4)?)?), 6)),
+            'U' => Some((char_bytes(char::from_u32(read_hex(chars, i + 2, 8)?)?), 10)),
+            'N' if python => {
+                if chars.get(i + 2) != Some(&'{') { return None; }
+                let close = (i + 3..chars.len()).find(|&j| chars[j] == '}')?;
+                let name: String = chars[i + 3..close].iter().collect();
+                Some((char_bytes(unicode_names2::character(&name)?), close - i + 1))
+            },
+            _ => None,
+        };
+    });
+}
+
+fn unescape_c(text: &str) -> String {
+    return unescape_common(text, false);
+}
+
+/// The contents of a Python string literal; both quote characters are escaped so the
+/// result is safe inside either kind of quotes.
+fn escape_python(text: &str) -> String {
+    return text.chars().map(|c| match c {
+        '\\' => "\\\\".to_string(),
+        '"' => "\\\"".to_string(),
+        '\'' => "\\'".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        c if c.is_ascii_control() => format!("\\x{:02x}", c as u32),
+        c => c.to_string(),
+    }).collect();
+}
+
+fn unescape_python(text: &str) -> String {
+    return unescape_common(text, true);
+}
+
+/// A complete single-quoted shell word. Nothing is special inside single quotes, so
+/// the only thing to handle is `'` itself, which becomes `'\''`.
+fn escape_shell(text: &str) -> String {
+    return format!("'{}'", text.replace('\'', "'\\''"));
+}
+
+/// Reverses shell quoting for a single word: single-quoted, double-quoted and
+/// backslash-escaped segments may be freely concatenated.
+fn unescape_shell(text: &str) -> String {
+    let chars: Vec<char> = text.trim_end_matches(['\n', '\r']).chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\'' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    result.push(chars[i]);
+                    i += 1;
+                }
+            },
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' && i + 1 < chars.len() && matches!(chars[i + 1], '$' | '`' | '"' | '\\' | '\n') {
+                        i += 1;
+                        if chars[i] != '\n' { result.push(chars[i]); }
+                    }
+                    else { result.push(chars[i]); }
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                if chars[i] != '\n' { result.push(chars[i]); }
+            },
+            c => result.push(c),
+        }
+        i += 1;
+    }
+    return result;
+}
+
+/// A complete SQL string literal, with embedded quotes doubled.
+fn escape_sql(text: &str) -> String {
+    return format!("'{}'", text.replace('\'', "''"));
+}
+
+fn unescape_sql(text: &str) -> String {
+    let trimmed = text.trim_end_matches(['\n', '\r']);
+    let inner = trimmed.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')).unwrap_or(trimmed);
+    return inner.replace("''", "'");
+}
+
+/// Drops the backslash in front of any non-alphanumeric character, which undoes
+/// `regex::escape` while leaving classes such as `\d` and `\w` alone.
+fn unescape_regex(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && let Some(&next) = chars.peek() && !next.is_alphanumeric() {
+            result.push(next);
+            chars.next();
+            continue;
+        }
+        result.push(c);
+    }
+    return result;
+}
+
+/// Treats the whole input as one RFC 4180 field, quoting it only when needed.
+fn escape_csv(text: &str) -> String {
+    let needs_quotes = text.contains([',', '"', '\n', '\r']) || text.starts_with(' ') || text.ends_with(' ');
+    if !needs_quotes { return text.to_string(); }
+    return format!("\"{}\"", text.replace('"', "\"\""));
+}
+
+fn unescape_csv(text: &str) -> String {
+    let trimmed = text.trim_end_matches(['\n', '\r']);
+    return match trimmed.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => trimmed.to_string(),
+    };
+}
+
+/// Newlines and tabs are written as character references because XML attribute
+/// value normalisation would otherwise turn them into spaces.
+fn escape_xml_attr(text: &str) -> String {
+    return text.chars().map(|c| match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        '\'' => "&apos;".to_string(),
+        '\n' => "&#10;".to_string(),
+        '\r' => "&#13;".to_string(),
+        '\t' => "&#9;".to_string(),
+        c => c.to_string(),
+    }).collect();
+}
+
+fn unescape_xml_attr(text: &str) -> String {
+    return match quick_xml::escape::unescape(text) {
+        Ok(result) => result.to_string(),
+        Err(e) => panic!("Unable to unescape XML attribute: {}", e),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: &[&str] = &[
+        "",
+        "plain ascii",
+        "quotes \" and ' and `backticks`",
+        "back\\slash \\n not a newline",
+        "C:\\x41\\u0041\\u{41}\\101\\N{DIGIT ONE}\\\\",
+        "new\nline\r\ntab\there",
+        "bell \u{07} nul \u{00} esc \u{1b} del \u{7f}",
+        "caf\u{e9} \u{65e5}\u{672c} \u{1f600}",
+        "$HOME `cmd` !history ; | & < > * ? [x] {y} ^ # ~ -",
+        " leading and trailing spaces ",
+        "ends with a newline\n",
+    ];
+
+    fn assert_round_trip(name: &str, escape: fn(&str) -> String, unescape: fn(&str) -> String) {
+        for sample in SAMPLES {
+            let escaped = escape(sample);
+            assert_eq!(unescape(&escaped), *sample, "{} did not round-trip {:?} (escaped as {:?})", name, sample, escaped);
+        }
+    }
+
+    #[test]
+    fn json_round_trips() {
+        assert_round_trip("json", escape_json, unescape_json);
+    }
+
+    #[test]
+    fn unescape_json_ignores_trailing_newline() {
+        assert_eq!(unescape_json("a\\tb\n"), "a\tb");
+        assert_eq!(unescape_json("\"a\\tb\"\r\n"), "a\tb");
+    }
+
+    #[test]
+    fn c_and_java_round_trip() {
+        assert_round_trip("c", escape_c, unescape_c);
+    }
+
+    #[test]
+    fn python_round_trips() {
+        assert_round_trip("python", escape_python, unescape_python);
+    }
+
+    #[test]
+    fn shell_round_trips() {
+        assert_round_trip("shell", escape_shell, unescape_shell);
+    }
+
+    #[test]
+    fn sql_round_trips() {
+        assert_round_trip("sql", escape_sql, unescape_sql);
+    }
+
+    #[test]
+    fn regex_round_trips() {
+        assert_round_trip("regex", regex::escape, unescape_regex);
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        assert_round_trip("csv", escape_csv, unescape_csv);
+    }
+
+    #[test]
+    fn xml_attr_round_trips() {
+        assert_round_trip("xml_attr", escape_xml_attr, unescape_xml_attr);
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        for all in [false, true] {
+            for sample in SAMPLES {
+                assert_eq!(unescape_unicode(&escape_unicode(sample, all)), *sample);
+                assert_eq!(unescape_unicode_braced(&escape_unicode_braced(sample, all)), *sample);
+                assert_eq!(unescape_hex(&escape_hex(sample, all)), *sample);
+                assert_eq!(unescape_octal(&escape_octal(sample, all)), *sample);
+                assert_eq!(unescape_unicode_name(&escape_unicode_name(sample, all)), *sample);
+            }
+        }
+    }
+}
@@ -1,13 +1,25 @@
-use serde_json::Result;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use lightningcss::error::ErrorLocation;
 use lightningcss::stylesheet::{StyleSheet, ParserOptions, MinifyOptions, PrinterOptions};
-use sqlformat::{format, FormatOptions, QueryParams};
+use lightningcss::targets::{Browsers, Features, Targets};
+use parcel_sourcemap::SourceMap;
+use sqlformat::{format, FormatOptions, Indent, QueryParams};
 use xmltree::Element;
-use quick_xml::events::Event;
+use crate::{dockerfile, graphql, html, js, protobuf, validation};
+use crate::layout::Layout;
+use crate::json::{self, JsonFormat};
+use crate::sql::{self, SqlDialect};
+use crate::utils;
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
+use serde::Deserialize;
 
 pub enum FormatOp {
-    AddSlashes, RemoveSlashes, FormatJson, FormatSql, FormatCss,
-    FormatXml, MinifyJson, MinifySql, MinifyCss, MinifyXml, Unknown,
+    AddSlashes, RemoveSlashes, FormatJson, FormatSql, FormatCss, FormatXml, FormatHtml, FormatYaml,
+    FormatToml, FormatIni, FormatMarkdownTable, FormatGraphql, FormatProtobuf, FormatDockerfile, MinifyJson, MinifySql, MinifyCss, MinifyXml, MinifyHtml,
+    MinifyJs, MinifyYaml, MinifyToml, MinifyIni, Unknown,
 }
 
 fn str_to_format_op(arg: &str) -> FormatOp {
@@ -18,16 +30,29 @@ fn str_to_format_op(arg: &str) -> FormatOp {
         "format_sql" => FormatOp::FormatSql,
         "format_css" => FormatOp::FormatCss,
         "format_xml" => FormatOp::FormatXml,
+        "format_html" => FormatOp::FormatHtml,
+        "format_yaml" => FormatOp::FormatYaml,
+        "format_toml" => FormatOp::FormatToml,
+        "format_ini" => FormatOp::FormatIni,
+        "format_markdown_table" => FormatOp::FormatMarkdownTable,
+        "format_graphql" => FormatOp::FormatGraphql,
+        "format_protobuf" => FormatOp::FormatProtobuf,
+        "format_dockerfile" => FormatOp::FormatDockerfile,
         "minify_json" => FormatOp::MinifyJson,
         "minify_sql" => FormatOp::MinifySql,
         "minify_css" => FormatOp::MinifyCss,
         "minify_xml" => FormatOp::MinifyXml,
+        "minify_html" => FormatOp::MinifyHtml,
+        "minify_js" => FormatOp::MinifyJs,
+        "minify_yaml" => FormatOp::MinifyYaml,
+        "minify_toml" => FormatOp::MinifyToml,
+        "minify_ini" => FormatOp::MinifyIni,
         _ => FormatOp::Unknown,
     }
 }
 
 pub fn select_format_option(args: &Vec<String>) -> FormatOp {
-    let format_ops: Vec<String> = vec!["add_slashes", "remove_slashes", "format_json", "format_sql", "format_css", "format_xml", "minify_json", "minify_sql", "minify_css", "minify_xml"].into_iter().map(|x| x.to_string()).collect();
+    let format_ops: Vec<String> = vec!["add_slashes", "remove_slashes", "format_json", "format_sql", "format_css", "format_xml", "format_html", "format_yaml", "format_toml", "format_ini", "format_markdown_table", "format_graphql", "format_protobuf", "format_dockerfile", "minify_json", "minify_sql", "minify_css", "minify_xml", "minify_html", "minify_js", "minify_yaml", "minify_toml", "minify_ini"].into_iter().map(|x| x.to_string()).collect();
     for arg in args {
         if format_ops.contains(&arg.to_lowercase()) {
             return str_to_format_op(arg);
@@ -36,135 +61,433 @@ pub fn select_format_option(args: &Vec<String>) -> FormatOp {
     return FormatOp::Unknown
 }
 
-pub fn handle_format_operation(text: &str, op: FormatOp) -> String {
+pub fn handle_format_operation(text: &str, op: FormatOp, args: &Vec<String>) -> String {
     return match op {
         FormatOp::AddSlashes => add_slashes(text),
         FormatOp::RemoveSlashes => remove_slashes(text),
-        FormatOp::FormatJson => format_json(text),
-        FormatOp::MinifyJson => minify_json(text),
-        FormatOp::FormatCss => format_css(text),
-        FormatOp::MinifyCss => minify_css(text),
-        FormatOp::FormatSql => format_sql(text),
-        FormatOp::MinifySql => minify_sql(text),
-        FormatOp::FormatXml => format_xml(text),
+        FormatOp::FormatJson => format_json(text, args),
+        FormatOp::MinifyJson => minify_json(text, args),
+        FormatOp::FormatCss => format_css(text, args),
+        FormatOp::MinifyCss => minify_css(text, args),
+        FormatOp::FormatSql => format_sql(text, args),
+        FormatOp::MinifySql => minify_sql(text, args),
+        FormatOp::FormatXml => format_xml(text, &utils::get_indent(args)),
         FormatOp::MinifyXml => minify_xml(text),
+        FormatOp::FormatHtml => html::format(text, &utils::get_indent(args)),
+        FormatOp::MinifyHtml => html::minify(text, args),
+        FormatOp::MinifyJs => minify_js(text, args),
+        FormatOp::FormatYaml => format_yaml(text),
+        FormatOp::MinifyYaml => minify_yaml(text),
+        FormatOp::FormatToml => format_toml(text),
+        FormatOp::MinifyToml => minify_toml(text),
+        FormatOp::FormatIni => format_ini(text),
+        FormatOp::MinifyIni => minify_ini(text),
+        FormatOp::FormatMarkdownTable => format_markdown_table(text),
+        FormatOp::FormatGraphql => format_graphql(text, args),
+        FormatOp::FormatProtobuf => format_protobuf(text, args),
+        FormatOp::FormatDockerfile => dockerfile::format(text, &Layout::from_args(args)),
         _ => panic!("Unknown format operation specified."),
     };
 }
 
+/// Like PHP's `addslashes`: backslashes, quotes and NUL bytes get a backslash.
 fn add_slashes(text: &str) -> String {
-    return text.chars().map(|c| 
-        if c == '\'' || c == '"' { format!("\\{}", c) }
-        else { c.to_string() }
-    ).collect();    
+    return text.chars().map(|c| match c {
+        '\'' | '"' | '\\' => format!("\\{}", c),
+        '\0' => "\\0".to_string(),
+        _ => c.to_string(),
+    }).collect();
 }
 
+/// Like PHP's `stripslashes`: every backslash escapes the character after it.
 fn remove_slashes(text: &str) -> String {
-    let mut result = text.replace("\\\"", "\"");
-    result = result.replace("\\'", "'");
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => result.push('\0'),
+            Some(next) => result.push(next),
+            None => {},
+        }
+    }
     return result;
 }
 
-fn format_json(text: &str) -> String {
-    let val: Result<serde_json::Value> = serde_json::from_str(text);
-    if let Ok(value) = val {
-        let res = serde_json::to_string_pretty(&value);
-        match res {
-            Err(e) => panic!("Unable to format JSON: {}", e),
-            Ok(result) => return result,
-        }
+fn select_json_format(args: &Vec<String>) -> JsonFormat {
+    return JsonFormat {
+        indent: utils::get_indent(args),
+        sort_keys: utils::has_option(args, "sort-keys"),
+        compact_arrays: utils::has_option(args, "compact-arrays"),
+        ascii: utils::has_option(args, "ascii"),
+        comments: utils::has_option(args, "keep-comments"),
+    };
+}
+
+fn with_trailing_newline(mut text: String, args: &Vec<String>) -> String {
+    if utils::has_option(args, "trailing-newline") {
+        text.push('\n');
     }
-    else { panic!("Unable to read JSON data to string (format_json).") }
+    return text;
+}
+
+/// Accepts JSONC and JSON5 as well as JSON. Key order and the original spelling of
+/// numbers are kept unless `--sort-keys` is given; `--keep-comments` carries comments over.
+fn format_json(text: &str, args: &Vec<String>) -> String {
+    return match json::parse_lenient(text) {
+        Ok(value) => with_trailing_newline(json::to_pretty(&value, &select_json_format(args)), args),
+        Err(e) => panic!("Unable to read JSON data (format_json): {}", e),
+    };
+}
+
+fn minify_json(text: &str, args: &Vec<String>) -> String {
+    return match json::parse_lenient(text) {
+        Ok(value) => with_trailing_newline(json::to_compact(&value, &select_json_format(args)), args),
+        Err(e) => panic!("Unable to read JSON data (minify_json): {}", e),
+    };
+}
+
+/// `--targets="<browserslist query>"` adds vendor prefixes and lowers syntax (nesting
+/// included) the listed browsers lack; `--lower-nesting` lowers nesting regardless.
+fn select_css_targets(args: &Vec<String>) -> Targets {
+    let mut targets = match utils::get_option_value(args, "targets") {
+        Some(query) => match Browsers::from_browserslist([query.as_str()]) {
+            Ok(browsers) => Targets { browsers, ..Targets::default() },
+            Err(e) => panic!("Unable to resolve browser targets '{}': {}", query, e),
+        },
+        None => Targets::default(),
+    };
+    if utils::has_option(args, "lower-nesting") {
+        targets.include |= Features::Nesting;
+    }
+    return targets;
+}
+
+fn css_error_location(loc: &Option<ErrorLocation>) -> String {
+    return match loc {
+        Some(loc) => format!(" at line {}, column {}", loc.line + 1, loc.column),
+        None => String::new(),
+    };
 }
 
-fn minify_json(text: &str) -> String {
-    let val: Result<serde_json::Value> = serde_json::from_str(text);
-    if let Ok(value) = val {
-        let res = serde_json::to_string(&value);
-        match res {
-            Err(e) => panic!("Unable to minify JSON: {}", e),
-            Ok(result) => return result,
+/// With `--source-map`, CSS output goes next to the input (`style.min.css` or
+/// `style.out.css`) rather than over it, so the map still points at the original source.
+pub fn inserted_extension(op: &FormatOp, args: &Vec<String>) -> Option<String> {
+    let ext = match op {
+        FormatOp::MinifyCss => "min",
+        FormatOp::FormatCss => "out",
+        _ => return None,
+    };
+    return utils::get_option_value(args, "source-map").map(|_| ext.to_string());
+}
+
+/// Parses nested rules (CSS Nesting, which also covers SCSS-style nesting without `&`
+/// suffixes) and reports the location of the first syntax error. `--lenient` skips
+/// invalid rules and declarations, listing each one on stderr instead.
+/// `--source-map=<file>` writes a source map there and links it from the output, which
+/// `main` then writes beside the input (see `inserted_extension`).
+fn print_css(text: &str, args: &Vec<String>, minify: bool) -> String {
+    let filename = utils::find_file_path(args).unwrap_or("stdin".to_string());
+    let warnings = Arc::new(RwLock::new(Vec::new()));
+    let lenient = utils::has_option(args, "lenient");
+    let options = ParserOptions {
+        filename: filename.clone(),
+        error_recovery: lenient,
+        warnings: if lenient { Some(warnings.clone()) } else { None },
+        ..ParserOptions::default()
+    };
+    let mut stylesheet = match StyleSheet::parse(text, options) {
+        Ok(stylesheet) => stylesheet,
+        Err(e) => panic!("Unable to parse CSS{}: {}", css_error_location(&e.loc), e.kind),
+    };
+    for warning in warnings.read().unwrap().iter() {
+        eprintln!("warning: skipped invalid CSS{}: {}", css_error_location(&warning.loc), warning.kind);
+    }
+    let targets = select_css_targets(args);
+    // Formatting leaves rules alone unless there is something to lower for the targets.
+    let transform = minify || utils::get_option_value(args, "targets").is_some() || utils::has_option(args, "lower-nesting");
+    if transform && let Err(e) = stylesheet.minify(MinifyOptions { targets, ..MinifyOptions::default() }) {
+        panic!("Unable to minify CSS{}: {}", css_error_location(&e.loc), e.kind);
+    }
+    let map_path = utils::get_option_value(args, "source-map");
+    let mut source_map = SourceMap::new("/");
+    if map_path.is_some() {
+        source_map.add_source(&filename);
+        if let Err(e) = source_map.set_source_content(0, text) {
+            panic!("Unable to create source map: {}", e);
         }
     }
-    else { panic!("Unable to read JSON data to string (minify_json).") }
+    let printer = PrinterOptions {
+        minify,
+        targets,
+        source_map: if map_path.is_some() { Some(&mut source_map) } else { None },
+        ..PrinterOptions::default()
+    };
+    let mut code = match stylesheet.to_css(printer) {
+        Ok(result) => result.code,
+        Err(e) => panic!("Unable to print CSS{}: {}", css_error_location(&e.loc), e.kind),
+    };
+    if let Some(map_path) = map_path {
+        let json = source_map.to_json(None).unwrap_or_else(|e| panic!("Unable to serialize source map: {}", e));
+        if let Err(e) = fs::write(&map_path, json) {
+            panic!("Unable to write source map {}: {}", map_path, e);
+        }
+        let name = Path::new(&map_path).file_name().map_or(map_path.clone(), |name| name.to_string_lossy().to_string());
+        code.push_str(&format!("\n/*# sourceMappingURL={} */\n", name));
+    }
+    return code;
+}
+
+/// Minifies the content of an inline `<style>` element, honouring `--targets`.
+pub fn minify_stylesheet(text: &str, args: &Vec<String>) -> Result<String, String> {
+    let mut stylesheet = StyleSheet::parse(text, ParserOptions::default())
+        .map_err(|e| format!("Unable to parse CSS{}: {}", css_error_location(&e.loc), e.kind))?;
+    let targets = select_css_targets(args);
+    stylesheet.minify(MinifyOptions { targets, ..MinifyOptions::default() })
+        .map_err(|e| format!("Unable to minify CSS{}: {}", css_error_location(&e.loc), e.kind))?;
+    let result = stylesheet.to_css(PrinterOptions { minify: true, targets, ..PrinterOptions::default() })
+        .map_err(|e| format!("Unable to print CSS{}: {}", css_error_location(&e.loc), e.kind))?;
+    return Ok(result.code);
+}
+
+/// Indented per `--indent=<n>`/`--tabs` like the other code formatters.
+fn format_css(text: &str, args: &Vec<String>) -> String {
+    return Layout::from_args(args).reindent(&print_css(text, args, false), 2);
+}
+
+fn minify_css(text: &str, args: &Vec<String>) -> String {
+    return print_css(text, args, true);
+}
+
+fn format_graphql(text: &str, args: &Vec<String>) -> String {
+    return match graphql::format(text, &Layout::from_args(args)) {
+        Ok(result) => result,
+        Err(e) => panic!("Unable to parse GraphQL: {}", e),
+    };
+}
+
+fn format_protobuf(text: &str, args: &Vec<String>) -> String {
+    return match protobuf::format(text, &Layout::from_args(args)) {
+        Ok(result) => result,
+        Err(e) => panic!("Unable to parse Protocol Buffers: {}", e),
+    };
+}
+
+/// `--mangle` shortens local names; `--module` parses the input as an ES module.
+fn minify_js(text: &str, args: &Vec<String>) -> String {
+    return match js::minify(text, utils::has_option(args, "mangle"), utils::has_option(args, "module")) {
+        Ok(code) => code,
+        Err(e) => panic!("Unable to parse JavaScript at {}", e),
+    };
+}
+
+fn select_sql_dialect(args: &Vec<String>) -> SqlDialect {
+    return match utils::get_option_value(args, "dialect") {
+        Some(name) => sql::str_to_dialect(&name),
+        None => SqlDialect::Generic,
+    };
+}
+
+/// `--param=name=value` fills named placeholders (`:name`, `@name`, `$name`); a bare
+/// `--param=value` fills positional ones (`?`, `$1`) in order.
+fn select_sql_params(args: &Vec<String>) -> QueryParams {
+    let params = utils::get_option_values(args, "param");
+    if params.is_empty() {
+        return QueryParams::None;
+    }
+    let named: Vec<(String, String)> = params.iter().filter_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') { return None; }
+        Some((name.to_string(), value.to_string()))
+    }).collect();
+    if named.len() == params.len() {
+        return QueryParams::Named(named);
+    }
+    return QueryParams::Indexed(params);
+}
+
+/// Options: `--indent=<n>`/`--tabs`, `--uppercase` or `--lowercase` keywords,
+/// `--lines-between=<n>` blank lines between queries and `--dialect=<dialect>`. `--width=<n>`
+/// keeps parenthesised blocks and argument lists inline up to that length.
+fn format_sql(sql: &str, args: &Vec<String>) -> String {
+    let layout = Layout::from_args(args);
+    let width = utils::get_option_value(args, "width").map(|_| layout.width);
+    let options = FormatOptions {
+        indent: if layout.indent == "\t" { Indent::Tabs } else { Indent::Spaces(layout.indent.len() as u8) },
+        uppercase: if utils::has_option(args, "uppercase") { Some(true) } else if utils::has_option(args, "lowercase") { Some(false) } else { None },
+        lines_between_queries: utils::get_usize_option(args, "lines-between", 1) as u8,
+        dialect: sql::formatter_dialect(&select_sql_dialect(args)),
+        max_inline_block: width.unwrap_or(50),
+        max_inline_arguments: width,
+        max_inline_top_level: width,
+        ..FormatOptions::default()
+    };
+    return format(sql, &select_sql_params(args), &options);
+}
+
+fn minify_sql(text: &str, args: &Vec<String>) -> String {
+    return sql::minify(text, &select_sql_dialect(args));
 }
 
-fn format_css(text: &str) -> String {
-    let stylesheet = StyleSheet::parse(text, ParserOptions::default()).unwrap();
-    let result = stylesheet.to_css(PrinterOptions::default()).unwrap();
-    return result.code;
+fn xml_text(bytes: &[u8]) -> String {
+    return String::from_utf8_lossy(bytes).to_string();
 }
 
-fn minify_css(text: &str) -> String {
-    let mut stylesheet = StyleSheet::parse(text, ParserOptions::default()).unwrap();
-    let _ = stylesheet.minify(MinifyOptions::default());
-    let result = stylesheet.to_css(PrinterOptions::default()).unwrap();
-    return result.code;
+/// Rebuilds a start tag from its raw name and attributes, keeping namespace
+/// prefixes and attribute values (including entity references) exactly as written.
+fn xml_tag(e: &BytesStart) -> String {
+    let mut tag = xml_text(e.name().as_ref());
+    for attr in e.attributes() {
+        let attr = attr.unwrap_or_else(|err| panic!("Unable to read XML attribute: {}", err));
+        let value = xml_text(&attr.value);
+        let quote = if value.contains('"') { '\'' } else { '"' };
+        tag.push_str(&format!(" {}={}{}{}", xml_text(attr.key.as_ref()), quote, value, quote));
+    }
+    return tag;
 }
 
-fn format_sql(sql: &str) -> String {
-    return format(sql, &QueryParams::None, &FormatOptions::default());
+/// An XML node with its source kept as written: text keeps its entity references, and
+/// comments, CDATA, processing instructions, the declaration and the doctype are stored
+/// already serialised.
+enum XmlNode {
+    Element { tag: String, name: String, empty: bool, preserve: bool, children: Vec<XmlNode> },
+    Text(String),
+    Other(String),
 }
 
-fn minify_sql(sql: &str) -> String {
-    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+/// `xml:space="preserve"` applies to the element and everything inside it until an
+/// `xml:space="default"` turns it off again.
+fn xml_space_preserve(e: &BytesStart, inherited: bool) -> bool {
+    return match e.try_get_attribute("xml:space") {
+        Ok(Some(attr)) => attr.value.as_ref() == b"preserve",
+        _ => inherited,
+    };
 }
 
-pub fn format_xml(input: &str) -> String {
+fn parse_xml(input: &str) -> Vec<XmlNode> {
     let mut reader = Reader::from_str(input);
-    reader.config_mut().trim_text(true);
-    let mut buf = Vec::new();
-    let mut output = String::new();
-    let mut indent_level = 0;
+    // Each open element with the offset of its start tag; the document itself is the bottom entry.
+    let mut stack: Vec<(Option<BytesStart>, bool, usize, Vec<XmlNode>)> = vec![(None, false, 0, Vec::new())];
     loop {
-        let event = reader.read_event_into(&mut buf);
-        match event {
-            Ok(Event::Start(e)) => {
-                output.push_str(&format!(
-                    "{}<{}>\n",
-                    "  ".repeat(indent_level),
-                    match String::from_utf8(e.name().as_ref().to_vec()) {
-                        Ok(name) => name,
-                        Err(_) => "[invalid tag]".to_string(),
-                    }
-                ));
-                indent_level += 1;
+        let offset = reader.buffer_position() as usize;
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(event) => event,
+            Err(e) => {
+                let (line, column) = validation::line_column(input, reader.error_position() as usize);
+                panic!("Unable to parse XML at line {}, column {}: {}", line, column, e);
+            },
+        };
+        let preserve = stack.last().is_some_and(|(_, preserve, _, _)| *preserve);
+        let node = match event {
+            Event::Start(e) => {
+                let preserve = xml_space_preserve(&e, preserve);
+                stack.push((Some(e.into_owned()), preserve, offset, Vec::new()));
+                continue;
+            },
+            Event::End(_) => match stack.pop() {
+                Some((Some(start), preserve, _, children)) => XmlNode::Element {
+                    tag: xml_tag(&start),
+                    name: xml_text(start.name().as_ref()),
+                    empty: false,
+                    preserve,
+                    children,
+                },
+                _ => continue,
+            },
+            Event::Empty(e) => XmlNode::Element {
+                tag: xml_tag(&e),
+                name: xml_text(e.name().as_ref()),
+                empty: true,
+                preserve: false,
+                children: Vec::new(),
+            },
+            Event::Text(e) => XmlNode::Text(xml_text(&e)),
+            Event::CData(e) => XmlNode::Other(format!("<![CDATA[{}]]>", xml_text(&e))),
+            Event::Comment(e) => XmlNode::Other(format!("<!--{}-->", xml_text(&e))),
+            Event::Decl(e) => XmlNode::Other(format!("<?{}?>", xml_text(&e))),
+            Event::PI(e) => XmlNode::Other(format!("<?{}?>", xml_text(&e))),
+            Event::DocType(e) => XmlNode::Other(format!("<!DOCTYPE {}>", xml_text(&e))),
+            Event::Eof => break,
+        };
+        if let Some((_, _, _, children)) = stack.last_mut() {
+            children.push(node);
+        }
+    }
+    if stack.len() > 1 && let Some((Some(start), _, offset, _)) = stack.last() {
+        let (line, column) = validation::line_column(input, *offset);
+        panic!("Unable to parse XML at line {}, column {}: <{}> is never closed", line, column, xml_text(start.name().as_ref()));
+    }
+    return stack.pop().map_or(Vec::new(), |(_, _, _, children)| children);
+}
+
+fn is_blank_xml_text(node: &XmlNode) -> bool {
+    return matches!(node, XmlNode::Text(text) if text.trim().is_empty());
+}
+
+/// Writes a node exactly as it appears in the source, apart from attribute quoting.
+fn write_xml_inline(node: &XmlNode, out: &mut String) {
+    match node {
+        XmlNode::Element { tag, empty: true, .. } => out.push_str(&format!("<{}/>", tag)),
+        XmlNode::Element { tag, name, children, .. } => {
+            out.push_str(&format!("<{}>", tag));
+            for child in children {
+                write_xml_inline(child, out);
             }
-            Ok(Event::End(e)) => {
-                indent_level = indent_level.saturating_sub(1); // avoid underflow
-                output.push_str(&format!(
-                    "{}</{}>\n",
-                    "  ".repeat(indent_level),
-                    match String::from_utf8(e.name().as_ref().to_vec()) {
-                        Ok(name) => name,
-                        Err(_) => "[invalid tag]".to_string(),
-                    }
-                ));
+            out.push_str(&format!("</{}>", name));
+        },
+        XmlNode::Text(text) | XmlNode::Other(text) => out.push_str(text),
+    }
+}
+
+/// Only elements whose children are all elements (or comments and the like) are
+/// broken over lines. Any element holding text, including mixed content, and every
+/// element under `xml:space="preserve"` is written as it was, since there whitespace
+/// is part of the content.
+fn write_xml_block(node: &XmlNode, depth: usize, indent: &str, lines: &mut Vec<String>) {
+    let pad = indent.repeat(depth);
+    match node {
+        XmlNode::Element { tag, name, empty: false, preserve, children } => {
+            let has_text = children.iter().any(|child| matches!(child, XmlNode::Text(_)) && !is_blank_xml_text(child));
+            let has_elements = children.iter().any(|child| matches!(child, XmlNode::Element { .. }));
+            if *preserve || has_text || !has_elements {
+                let mut line = pad;
+                write_xml_inline(node, &mut line);
+                lines.push(line);
+                return;
             }
-            Ok(Event::Text(e)) => {
-                match e.unescape() {
-                    Ok(text) => {
-                        let trimmed = text.trim();
-                        if !trimmed.is_empty() {
-                            output.push_str(&format!("{}{}\n", "  ".repeat(indent_level), trimmed));
-                        }
-                    }
-                    Err(_) => output.push_str(&format!(
-                        "{}[invalid text]\n",
-                        "  ".repeat(indent_level)
-                    )),
-                }
+            lines.push(format!("{}<{}>", pad, tag));
+            for child in children.iter().filter(|child| !is_blank_xml_text(child)) {
+                write_xml_block(child, depth + 1, indent, lines);
             }
-            Ok(Event::Eof) => break,
-            Err(_) => {
-                output.push_str("[error reading XML stream]\n");
-                break;
+            lines.push(format!("{}</{}>", pad, name));
+        },
+        XmlNode::Text(text) => {
+            if !text.trim().is_empty() {
+                lines.push(format!("{}{}", pad, text.trim()));
             }
-            _ => {} // Ignore comments, declarations, etc.
-        }
-        buf.clear();
+        },
+        _ => {
+            let mut line = pad;
+            write_xml_inline(node, &mut line);
+            lines.push(line);
+        },
+    }
+}
+
+/// Lossless pretty-printer: attributes, namespaces, comments, CDATA, processing
+/// instructions, the doctype and the `<?xml?>` declaration are all kept. Whitespace-only
+/// text between child elements is treated as layout and replaced by the new
+/// indentation; text itself is never trimmed or re-wrapped.
+pub fn format_xml(input: &str, indent: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for node in parse_xml(input) {
+        write_xml_block(&node, 0, indent, &mut lines);
     }
-    return output;
+    return lines.join("\n");
 }
 
 fn clean_output(output: &str) -> String {
@@ -194,3 +517,347 @@ fn minify_xml(input: &str) -> String {
     return clean_output(&output);
 }
 
+
+fn yaml_documents(text: &str) -> Vec<serde_yaml::Value> {
+    return serde_yaml::Deserializer::from_str(text)
+        .map(|document| serde_yaml::Value::deserialize(document).unwrap_or_else(|e| panic!("Unable to parse YAML: {}", e)))
+        .collect();
+}
+
+/// Re-emits every document with two-space indentation and minimal quoting. Comments
+/// are not kept and anchors are expanded.
+fn format_yaml(text: &str) -> String {
+    let documents: Vec<String> = yaml_documents(text).iter()
+        .map(|document| serde_yaml::to_string(document).unwrap_or_else(|e| panic!("Unable to write YAML: {}", e)))
+        .collect();
+    return documents.join("---\n");
+}
+
+/// Strings that read back as the same string are left plain; anything that could be
+/// taken for another type or for flow syntax is double-quoted.
+fn yaml_flow_string(text: &str, out: &mut String) {
+    let plain = text.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ' '))
+        && text.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '/')
+        && !text.ends_with(' ')
+        // YAML 1.1 readers still take these for booleans.
+        && !["y", "n", "yes", "no", "on", "off"].contains(&text.to_lowercase().as_str())
+        && serde_yaml::from_str::<serde_yaml::Value>(text).is_ok_and(|value| value.as_str() == Some(text));
+    if plain {
+        out.push_str(text);
+    }
+    else {
+        // A JSON string is also a valid YAML double-quoted scalar.
+        out.push_str(&serde_json::to_string(text).unwrap_or_else(|e| panic!("Unable to write YAML string: {}", e)));
+    }
+}
+
+fn write_yaml_flow(value: &serde_yaml::Value, out: &mut String) {
+    match value {
+        serde_yaml::Value::Null => out.push_str("null"),
+        serde_yaml::Value::Bool(b) => out.push_str(&b.to_string()),
+        serde_yaml::Value::Number(n) => out.push_str(&n.to_string()),
+        serde_yaml::Value::String(s) => yaml_flow_string(s, out),
+        serde_yaml::Value::Sequence(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 { out.push_str(", "); }
+                write_yaml_flow(item, out);
+            }
+            out.push(']');
+        },
+        serde_yaml::Value::Mapping(map) => {
+            out.push('{');
+            for (idx, (key, item)) in map.iter().enumerate() {
+                if idx > 0 { out.push_str(", "); }
+                write_yaml_flow(key, out);
+                out.push_str(": ");
+                write_yaml_flow(item, out);
+            }
+            out.push('}');
+        },
+        serde_yaml::Value::Tagged(tagged) => {
+            out.push_str(&format!("{} ", tagged.tag));
+            write_yaml_flow(&tagged.value, out);
+        },
+    }
+}
+
+/// Flow style, one line per document. `.nan`, `.inf`, tags and non-string keys are
+/// kept, since the output is still YAML.
+fn minify_yaml(text: &str) -> String {
+    let documents: Vec<String> = yaml_documents(text).iter().map(|document| {
+        let mut line = String::new();
+        write_yaml_flow(document, &mut line);
+        line
+    }).collect();
+    return documents.join("\n---\n");
+}
+
+fn toml_table(text: &str) -> toml::Table {
+    return match toml::from_str(text) {
+        Ok(table) => table,
+        Err(e) => panic!("Unable to parse TOML: {}", e),
+    };
+}
+
+/// Keeps key order; comments are not kept.
+fn format_toml(text: &str) -> String {
+    return toml::to_string_pretty(&toml_table(text)).unwrap_or_else(|e| panic!("Unable to write TOML: {}", e));
+}
+
+fn minify_toml(text: &str) -> String {
+    let output = toml::to_string(&toml_table(text)).unwrap_or_else(|e| panic!("Unable to write TOML: {}", e));
+    return output.lines().filter(|line| !line.is_empty()).collect::<Vec<&str>>().join("\n");
+}
+
+enum IniLine {
+    Section(String),
+    Entry(String, char, String),
+    /// An indented line right after an entry, which continues that entry's value.
+    Continuation(String),
+    Comment(String),
+    Other(String),
+    Blank,
+}
+
+fn parse_ini_line(line: &str) -> IniLine {
+    let line = line.trim();
+    if line.is_empty() {
+        return IniLine::Blank;
+    }
+    if line.starts_with(';') || line.starts_with('#') {
+        return IniLine::Comment(line.to_string());
+    }
+    if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        return IniLine::Section(name.trim().to_string());
+    }
+    let delimiter = if line.contains('=') { '=' } else { ':' };
+    return match line.split_once(delimiter) {
+        Some((key, value)) => IniLine::Entry(key.trim().to_string(), delimiter, value.trim().to_string()),
+        None => IniLine::Other(line.to_string()),
+    };
+}
+
+/// Parses every line, treating a line that directly follows an entry (or another
+/// continuation) and is indented deeper than that entry's key as part of its value, as
+/// Python's configparser does. Keys indented alike (`\tname = ...` in a gitconfig) stay
+/// separate entries.
+fn parse_ini(text: &str) -> Vec<IniLine> {
+    let mut lines: Vec<IniLine> = Vec::new();
+    let mut key_indent = 0;
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let continues = matches!(lines.last(), Some(IniLine::Entry(..) | IniLine::Continuation(_)))
+            && indent > key_indent
+            && !line.trim().is_empty();
+        if continues {
+            lines.push(IniLine::Continuation(line.trim().to_string()));
+            continue;
+        }
+        let parsed = parse_ini_line(line);
+        if let IniLine::Entry(..) = parsed {
+            key_indent = indent;
+        }
+        lines.push(parsed);
+    }
+    return lines;
+}
+
+/// `key = value` with single spaces, one blank line before each section and no runs of
+/// blank lines. Comments are kept and continuation lines are indented by four spaces.
+fn format_ini(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in parse_ini(text) {
+        let after_blank = lines.last().is_none_or(|last| last.is_empty());
+        match line {
+            IniLine::Blank if !after_blank => lines.push(String::new()),
+            IniLine::Blank => {},
+            IniLine::Section(name) => {
+                if !after_blank {
+                    lines.push(String::new());
+                }
+                lines.push(format!("[{}]", name));
+            },
+            IniLine::Entry(key, '=', value) => lines.push(format!("{} = {}", key, value).trim_end().to_string()),
+            IniLine::Entry(key, delimiter, value) => lines.push(format!("{}{} {}", key, delimiter, value).trim_end().to_string()),
+            IniLine::Continuation(text) => lines.push(format!("    {}", text)),
+            IniLine::Comment(text) | IniLine::Other(text) => lines.push(text),
+        }
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    return lines.join("\n");
+}
+
+fn minify_ini(text: &str) -> String {
+    let lines: Vec<String> = parse_ini(text).into_iter().filter_map(|line| match line {
+        IniLine::Section(name) => Some(format!("[{}]", name)),
+        IniLine::Entry(key, delimiter, value) => Some(format!("{}{}{}", key, delimiter, value)),
+        IniLine::Continuation(text) => Some(format!(" {}", text)),
+        IniLine::Other(text) => Some(text),
+        IniLine::Comment(_) | IniLine::Blank => None,
+    }).collect();
+    return lines.join("\n");
+}
+
+/// Splits a pipe-table row into trimmed cells, leaving escaped pipes and pipes inside
+/// code spans alone.
+fn markdown_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = vec![String::new()];
+    let mut code = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        if c == '|' && !code && !escaped {
+            cells.push(String::new());
+        }
+        else if let Some(cell) = cells.last_mut() {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+        if c == '`' {
+            code = !code;
+        }
+    }
+    if cells.len() > 1 && cells.last().is_some_and(|cell| cell.trim().is_empty()) {
+        cells.pop();
+    }
+    return cells.iter().map(|cell| cell.trim().to_string()).collect();
+}
+
+fn is_markdown_delimiter_row(cells: &Vec<String>) -> bool {
+    return cells.iter().all(|cell| {
+        let dashes = cell.strip_prefix(':').unwrap_or(cell);
+        let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+        !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+    });
+}
+
+fn format_markdown_rows(rows: &Vec<Vec<String>>) -> Vec<String> {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let alignments: Vec<(bool, bool)> = (0..columns).map(|col| match rows[1].get(col) {
+        Some(cell) => (cell.starts_with(':'), cell.ends_with(':')),
+        None => (false, false),
+    }).collect();
+    let widths: Vec<usize> = (0..columns).map(|col| {
+        rows.iter().enumerate()
+            .filter(|(idx, _)| *idx != 1)
+            .map(|(_, row)| row.get(col).map_or(0, |cell| cell.chars().count()))
+            .max()
+            .unwrap_or(0)
+            .max(3)
+    }).collect();
+    return rows.iter().enumerate().map(|(idx, row)| {
+        let cells: Vec<String> = (0..columns).map(|col| {
+            let width = widths[col];
+            let (left, right) = alignments[col];
+            if idx == 1 {
+                let dashes = "-".repeat(width - left as usize - right as usize);
+                return format!("{}{}{}", if left { ":" } else { "" }, dashes, if right { ":" } else { "" });
+            }
+            let cell = row.get(col).map_or("", |cell| cell.as_str());
+            let pad = width - cell.chars().count();
+            return match (left, right) {
+                (true, true) => format!("{}{}{}", " ".repeat(pad / 2), cell, " ".repeat(pad - pad / 2)),
+                (false, true) => format!("{}{}", " ".repeat(pad), cell),
+                _ => format!("{}{}", cell, " ".repeat(pad)),
+            };
+        }).collect();
+        format!("| {} |", cells.join(" | "))
+    }).collect();
+}
+
+/// The opening run of a fenced code block (three or more backticks or tildes).
+fn markdown_fence(line: &str) -> Option<String> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let run: String = line.chars().take_while(|c| *c == marker).collect();
+    return if run.chars().count() >= 3 { Some(run) } else { None };
+}
+
+/// Pads every pipe table into aligned columns, honouring the `:---:` alignment markers.
+/// Everything outside tables, including fenced code blocks, is left untouched. Widths count characters, so wide CJK
+/// text may still look uneven.
+fn format_markdown_table(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut result: Vec<String> = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut idx = 0;
+    while idx < lines.len() {
+        let trimmed = lines[idx].trim_start();
+        if let Some(open) = &fence {
+            if trimmed.starts_with(open.as_str()) && trimmed.trim_start_matches(open.chars().next().unwrap_or('`')).trim().is_empty() {
+                fence = None;
+            }
+            result.push(lines[idx].to_string());
+            idx += 1;
+            continue;
+        }
+        if let Some(marker) = markdown_fence(trimmed) {
+            fence = Some(marker);
+            result.push(lines[idx].to_string());
+            idx += 1;
+            continue;
+        }
+        // A delimiter row needs a pipe and one cell per header cell, so a setext heading
+        // underline (`---`) after a line that happens to contain a pipe is not a table.
+        let is_table = lines[idx].contains('|')
+            && lines.get(idx + 1).is_some_and(|next| {
+                let cells = markdown_cells(next);
+                next.contains('|') && is_markdown_delimiter_row(&cells) && cells.len() == markdown_cells(lines[idx]).len()
+            });
+        if !is_table {
+            result.push(lines[idx].to_string());
+            idx += 1;
+            continue;
+        }
+        let mut rows = Vec::new();
+        while idx < lines.len() && lines[idx].contains('|') && !lines[idx].trim().is_empty() {
+            rows.push(markdown_cells(lines[idx]));
+            idx += 1;
+        }
+        result.extend(format_markdown_rows(&rows));
+    }
+    return result.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_table_needs_matching_delimiter_row() {
+        let setext = "Title | sub\n---";
+        assert_eq!(format_markdown_table(setext), setext);
+        let short = "|x|y|\n|-|";
+        assert_eq!(format_markdown_table(short), short);
+        assert_eq!(format_markdown_table("|a|bb|\n|:-|-:|\n|ccc|d|"), "| a   |  bb |\n| :-- | --: |\n| ccc |   d |");
+    }
+
+    #[test]
+    fn markdown_table_skips_fenced_code() {
+        let text = "```\n|a|b|\n|-|-|\n```\n~~~~\n|c|d|\n|-|-|\n~~~\n~~~~";
+        assert_eq!(format_markdown_table(text), text);
+    }
+
+    #[test]
+    fn minify_yaml_keeps_yaml_only_values() {
+        let text = "a: .nan\nb: -.inf\nc: !custom x\n1: one\n? [1, 2]\n: pair\nd: 'true'\ne: yes\nf: plain text\n";
+        let minified = minify_yaml(text);
+        assert_eq!(minified, "{a: .nan, b: -.inf, c: !custom x, 1: one, [1, 2]: pair, d: \"true\", e: \"yes\", f: plain text}");
+        let original: serde_yaml::Value = serde_yaml::from_str(text).unwrap();
+        let reparsed: serde_yaml::Value = serde_yaml::from_str(&minified).unwrap();
+        assert_eq!(format!("{:?}", original), format!("{:?}", reparsed));
+    }
+
+    #[test]
+    fn ini_continuation_lines_stay_with_their_entry() {
+        let text = "[s]\nkey = first\n   second\n\tthird\nother=1\n\n  [t]\n  k = v";
+        assert_eq!(format_ini(text), "[s]\nkey = first\n    second\n    third\nother = 1\n\n[t]\nk = v");
+        assert_eq!(minify_ini(text), "[s]\nkey=first\n second\n third\nother=1\n[t]\nk=v");
+        let gitconfig = "[user]\n\tname = Jane\n\temail = j@x.org\n\tsigningkey =\n\t\tABC123";
+        assert_eq!(format_ini(gitconfig), "[user]\nname = Jane\nemail = j@x.org\nsigningkey =\n    ABC123");
+        assert_eq!(minify_ini(gitconfig), "[user]\nname=Jane\nemail=j@x.org\nsigningkey=\n ABC123");
+    }
+}
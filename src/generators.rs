@@ -0,0 +1,135 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{rngs::StdRng, seq::{IndexedRandom, SliceRandom}, Rng};
+use ulid::Ulid;
+use uuid::{Builder, Uuid};
+use crate::utils;
+
+pub enum GeneratorOp {
+    UuidV4, UuidV5, UuidV7, Ulid, NanoId, HexToken, Base64Token, Password, Unknown,
+}
+
+const NANOID_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.<>/?~";
+const AMBIGUOUS: &str = "Il1O0o|`'\";:,.";
+
+fn str_to_generator_op(arg: &str) -> GeneratorOp {
+    return match arg {
+        "uuid" => GeneratorOp::UuidV4,
+        "uuid_v4" => GeneratorOp::UuidV4,
+        "uuid_v5" => GeneratorOp::UuidV5,
+        "uuid_v7" => GeneratorOp::UuidV7,
+        "ulid" => GeneratorOp::Ulid,
+        "nanoid" => GeneratorOp::NanoId,
+        "hex_token" => GeneratorOp::HexToken,
+        "base64_token" => GeneratorOp::Base64Token,
+        "generate_password" => GeneratorOp::Password,
+        _ => GeneratorOp::Unknown,
+    }
+}
+
+pub fn select_generator_option(args: &Vec<String>) -> GeneratorOp {
+    let generator_options: Vec<String> = vec!["uuid", "uuid_v4", "uuid_v5", "uuid_v7", "ulid", "nanoid", "hex_token", "base64_token", "generate_password"].into_iter().map(|x| x.to_string()).collect();
+    for arg in args {
+        if generator_options.contains(&arg.to_lowercase()) {
+            return str_to_generator_op(arg);
+        }
+    }
+    return GeneratorOp::Unknown
+}
+
+/// Generators ignore their input. `--count=<n>` produces one value per line and
+/// `--seed=<n>` makes the random parts reproducible.
+pub fn handle_generator_operation(op: GeneratorOp, args: &Vec<String>) -> String {
+    let count = utils::get_usize_option(args, "count", 1);
+    let mut rng = utils::get_rng(args);
+    if let GeneratorOp::UuidV5 = op {
+        if count != 1 {
+            panic!("uuid_v5 is derived from --namespace and --name only, so --count would just repeat it.");
+        }
+        return uuid_v5(args);
+    }
+    let values: Vec<String> = (0..count).map(|_| match op {
+        GeneratorOp::UuidV4 => uuid_v4(&mut rng),
+        GeneratorOp::UuidV7 => uuid_v7(&mut rng),
+        GeneratorOp::Ulid => ulid(&mut rng),
+        GeneratorOp::NanoId => nanoid(&mut rng, utils::get_usize_option(args, "length", 21)),
+        GeneratorOp::HexToken => hex::encode(random_bytes(&mut rng, utils::get_usize_option(args, "length", 32))),
+        GeneratorOp::Base64Token => URL_SAFE_NO_PAD.encode(random_bytes(&mut rng, utils::get_usize_option(args, "length", 32))),
+        GeneratorOp::Password => password(&mut rng, args),
+        _ => panic!("Unknown generator operation specified."),
+    }).collect();
+    return values.join("\n");
+}
+
+fn random_bytes(rng: &mut StdRng, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rng.fill(bytes.as_mut_slice());
+    return bytes;
+}
+
+fn now_millis() -> u64 {
+    return chrono::Utc::now().timestamp_millis() as u64;
+}
+
+fn uuid_v4(rng: &mut StdRng) -> String {
+    return Builder::from_random_bytes(rng.random()).into_uuid().to_string();
+}
+
+/// The timestamp is always the current time; only the random bits follow `--seed`.
+fn uuid_v7(rng: &mut StdRng) -> String {
+    return Builder::from_unix_timestamp_millis(now_millis(), &rng.random()).into_uuid().to_string();
+}
+
+fn ulid(rng: &mut StdRng) -> String {
+    return Ulid::from_parts(now_millis(), rng.random()).to_string();
+}
+
+/// `--namespace` is `dns`, `url`, `oid`, `x500` or a UUID; `--name` is the name to hash.
+fn uuid_v5(args: &Vec<String>) -> String {
+    let namespace = utils::get_option_value(args, "namespace").unwrap_or("dns".to_string());
+    let namespace = match namespace.to_lowercase().as_str() {
+        "dns" => Uuid::NAMESPACE_DNS,
+        "url" => Uuid::NAMESPACE_URL,
+        "oid" => Uuid::NAMESPACE_OID,
+        "x500" => Uuid::NAMESPACE_X500,
+        other => Uuid::parse_str(other).unwrap_or_else(|e| panic!("Invalid namespace UUID '{}': {}", other, e)),
+    };
+    let name = utils::get_option_value(args, "name").expect("uuid_v5 requires --name=<name>.");
+    return Uuid::new_v5(&namespace, name.as_bytes()).to_string();
+}
+
+fn nanoid(rng: &mut StdRng, len: usize) -> String {
+    return (0..len).map(|_| *NANOID_ALPHABET.choose(rng).unwrap() as char).collect();
+}
+
+/// `--length` (default 20), `--classes` as a comma list of `lower,upper,digits,symbols`
+/// (default all four) and `--exclude-ambiguous` to drop look-alike characters.
+/// Every requested class is guaranteed to appear at least once.
+fn password(rng: &mut StdRng, args: &Vec<String>) -> String {
+    let length = utils::get_usize_option(args, "length", 20);
+    let classes = utils::get_option_value(args, "classes").unwrap_or("lower,upper,digits,symbols".to_string());
+    let exclude_ambiguous = utils::has_option(args, "exclude-ambiguous");
+    let pools: Vec<Vec<char>> = classes.split(',').map(|class| {
+        let chars = match class.trim() {
+            "lower" => LOWER,
+            "upper" => UPPER,
+            "digits" => DIGITS,
+            "symbols" => SYMBOLS,
+            other => panic!("Unknown character class '{}'. Use lower, upper, digits or symbols.", other),
+        };
+        chars.chars().filter(|c| !exclude_ambiguous || !AMBIGUOUS.contains(*c)).collect()
+    }).collect();
+    if length < pools.len() {
+        panic!("A password of length {} cannot contain all {} character classes.", length, pools.len());
+    }
+    let all: Vec<char> = pools.concat();
+    let mut result: Vec<char> = pools.iter().map(|pool| *pool.choose(rng).unwrap()).collect();
+    while result.len() < length {
+        result.push(*all.choose(rng).unwrap());
+    }
+    result.shuffle(rng);
+    return result.into_iter().collect();
+}
@@ -0,0 +1,392 @@
+use crate::layout::{Layout, Printer};
+
+const DEFINITION_KEYWORDS: [&str; 13] = [
+    "query", "mutation", "subscription", "fragment", "schema", "scalar", "type", "interface", "union", "enum",
+    "input", "directive", "extend",
+];
+
+#[derive(PartialEq)]
+enum Kind {
+    Name, Punct, Str, Comment,
+}
+
+struct Token {
+    kind: Kind,
+    text: String,
+    /// Line breaks between this token and the previous one.
+    newlines: usize,
+}
+
+fn is_name_char(c: char) -> bool {
+    return c.is_ascii_alphanumeric() || c == '_';
+}
+
+/// Commas are insignificant in GraphQL and kept only as tokens so that lists keep the
+/// separators they were written with.
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut newlines = 0;
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let kind = if c == '\n' {
+            newlines += 1;
+            line += 1;
+            i += 1;
+            continue;
+        }
+        else if c.is_whitespace() || c == '\u{feff}' {
+            i += 1;
+            continue;
+        }
+        else if c == '#' {
+            while i < chars.len() && chars[i] != '\n' { i += 1; }
+            Kind::Comment
+        }
+        else if c == '"' && chars.get(i + 1) == Some(&'"') && chars.get(i + 2) == Some(&'"') {
+            i += 3;
+            while i < chars.len() && !(chars[i] == '"' && chars.get(i + 1) == Some(&'"') && chars.get(i + 2) == Some(&'"')) {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            if i >= chars.len() {
+                return Err(format!("Unterminated block string starting on line {}", line));
+            }
+            i += 3;
+            Kind::Str
+        }
+        else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' && chars[i] != '\n' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            if chars.get(i) != Some(&'"') {
+                return Err(format!("Unterminated string on line {}", line));
+            }
+            i += 1;
+            Kind::Str
+        }
+        else if c == '.' && chars.get(i + 1) == Some(&'.') && chars.get(i + 2) == Some(&'.') {
+            i += 3;
+            Kind::Punct
+        }
+        else if "!$&():=@[]{}|,".contains(c) {
+            i += 1;
+            Kind::Punct
+        }
+        else if is_name_char(c) || c == '-' {
+            i += 1;
+            while i < chars.len() && (is_name_char(chars[i]) || (c.is_ascii_digit() || c == '-') && matches!(chars[i], '.' | '+' | '-')) {
+                i += 1;
+            }
+            Kind::Name
+        }
+        else {
+            return Err(format!("Unexpected character '{}' on line {}", c, line));
+        };
+        let text: String = chars[start..i].iter().collect();
+        line += text.matches('\n').count();
+        tokens.push(Token { kind, text, newlines });
+        newlines = 0;
+    }
+    return Ok(tokens);
+}
+
+fn space_before(prev: Option<&Token>, token: &Token) -> bool {
+    let prev = match prev {
+        Some(prev) => prev,
+        None => return false,
+    };
+    return match (prev.text.as_str(), token.text.as_str()) {
+        ("...", next) => next == "on" || next == "@" || next == "{",
+        (_, ")" | "]" | "!" | ":" | "," | "(") => false,
+        ("(" | "[" | "$" | "@", _) => false,
+        _ => true,
+    };
+}
+
+/// Index of the bracket closing the one opened at `open`.
+fn matching(tokens: &[Token], open: usize) -> Result<usize, String> {
+    let mut depth = 0;
+    for (idx, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != Kind::Punct {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(idx);
+                }
+            },
+            _ => {},
+        }
+    }
+    return Err(format!("Unclosed '{}'", tokens[open].text));
+}
+
+fn render_inline(tokens: &[&Token]) -> String {
+    let mut result = String::new();
+    let mut prev: Option<&Token> = None;
+    for token in tokens {
+        if space_before(prev, token) {
+            result.push(' ');
+        }
+        result.push_str(&token.text);
+        prev = Some(token);
+    }
+    return result;
+}
+
+/// Splits the inside of an argument or variable list into one entry per argument.
+fn split_arguments(tokens: &[Token]) -> Vec<Vec<&Token>> {
+    let mut items: Vec<Vec<&Token>> = Vec::new();
+    let mut depth = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        let prev = if idx > 0 { Some(&tokens[idx - 1]) } else { None };
+        let starts = depth == 0 && match token.kind {
+            Kind::Comment => true,
+            Kind::Str => prev.is_none_or(|prev| prev.text != ":" && prev.text != "="),
+            Kind::Name => tokens.get(idx + 1).is_some_and(|next| next.text == ":") && prev.is_none_or(|prev| prev.text != "$"),
+            Kind::Punct => token.text == "$" && prev.is_none_or(|prev| prev.text != ":" && prev.text != "="),
+        };
+        let continues_description = prev.is_some_and(|prev| prev.kind == Kind::Str && depth == 0);
+        if items.is_empty() || starts && !continues_description && items.last().is_some_and(|item| !item.is_empty()) {
+            items.push(Vec::new());
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" if token.kind == Kind::Punct => depth += 1,
+            ")" | "]" | "}" if token.kind == Kind::Punct => depth -= 1,
+            "," if depth == 0 => continue,
+            _ => {},
+        }
+        if let Some(item) = items.last_mut() {
+            item.push(token);
+        }
+    }
+    return items;
+}
+
+/// Prints `(...)` on the current line when it fits, otherwise one argument per line.
+fn print_arguments(out: &mut Printer, tokens: &[Token]) {
+    let items = split_arguments(tokens);
+    let has_comment = tokens.iter().any(|token| token.kind == Kind::Comment);
+    let inline = format!("({})", items.iter().map(|item| render_inline(item)).collect::<Vec<String>>().join(", "));
+    if !has_comment && out.fits(&inline) {
+        out.push(&inline, false);
+        return;
+    }
+    out.push("(", false);
+    out.flush();
+    out.depth += 1;
+    for item in items {
+        let (comments, values): (Vec<&Token>, Vec<&Token>) = item.into_iter().partition(|token| token.kind == Kind::Comment);
+        for comment in comments {
+            out.push_line(&comment.text);
+        }
+        if !values.is_empty() {
+            out.push_line(&render_inline(&values));
+        }
+    }
+    out.depth -= 1;
+    out.push(")", false);
+}
+
+/// Block strings are re-indented to the current depth; other tokens are printed as is.
+/// The closing `"""` on a line of its own does not count towards the common margin.
+fn push_string(out: &mut Printer, text: &str) {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() < 2 {
+        out.push(text, true);
+        return;
+    }
+    let margin = lines[1..].iter()
+        .filter(|line| !line.trim().is_empty() && line.trim() != "\"\"\"")
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let pad = out.layout.indent.repeat(out.depth);
+    let mut result = lines[0].trim_end().to_string();
+    for line in &lines[1..] {
+        let line = line.get(margin..).unwrap_or(line.trim_start()).trim_end();
+        result.push('\n');
+        if !line.is_empty() {
+            result.push_str(&pad);
+            result.push_str(line);
+        }
+    }
+    out.push(&result, true);
+}
+
+/// Whether `token` begins a new field, enum value or input value inside braces.
+fn starts_item(prev: Option<&Token>, token: &Token) -> bool {
+    let prev = match prev {
+        Some(prev) => prev,
+        None => return false,
+    };
+    return match token.kind {
+        Kind::Str => true,
+        Kind::Punct => token.text == "...",
+        Kind::Name => prev.kind == Kind::Str
+            || !matches!(prev.text.as_str(), ":" | "@" | "=" | "..." | "on" | "$" | "|" | "&" | "implements" | "{"),
+        Kind::Comment => false,
+    };
+}
+
+/// `current` is the keyword of the definition being printed; scalars, unions and
+/// directives have no body, so a brace after one opens an anonymous query.
+fn starts_definition(prev: Option<&Token>, token: &Token, current: &str) -> bool {
+    let prev = match prev {
+        Some(prev) => prev,
+        None => return false,
+    };
+    if prev.text == "extend" || prev.kind == Kind::Str {
+        return false;
+    }
+    return match token.kind {
+        Kind::Str => true,
+        Kind::Name => DEFINITION_KEYWORDS.contains(&token.text.as_str()),
+        Kind::Punct => token.text == "{" && (prev.text == "}" || matches!(current, "scalar" | "union" | "directive")),
+        Kind::Comment => false,
+    };
+}
+
+/// One field per line inside selection sets and type bodies, a blank line between
+/// definitions, and argument lists kept inline while they fit the line width. Comments
+/// and single blank lines between fields are kept.
+pub fn format(source: &str, layout: &Layout) -> Result<String, String> {
+    let tokens = tokenize(source)?;
+    let mut out = Printer::new(layout);
+    let mut prev: Option<&Token> = None;
+    let mut definition = tokens.first().map_or(String::new(), |token| token.text.clone());
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        if token.kind == Kind::Comment {
+            if token.newlines == 0 && !out.line.is_empty() {
+                out.push(&token.text, true);
+                out.flush();
+            }
+            else {
+                if token.newlines > 1 {
+                    out.blank();
+                }
+                out.push_line(&token.text);
+            }
+            idx += 1;
+            continue;
+        }
+        if out.depth == 0 && starts_definition(prev, token, &definition) {
+            out.blank();
+            definition = if token.kind == Kind::Name { token.text.clone() } else { String::new() };
+        }
+        else if out.depth == 0 && prev.is_some_and(|prev| prev.kind == Kind::Str) {
+            // The definition's description stays on its own line above it.
+            out.flush();
+            if token.kind == Kind::Name && token.text != "extend" {
+                definition = token.text.clone();
+            }
+        }
+        else if out.depth == 0 && prev.is_some_and(|prev| prev.text == "extend") {
+            definition = token.text.clone();
+        }
+        else if out.depth > 0 && starts_item(prev, token) {
+            if token.newlines > 1 {
+                out.blank();
+            }
+            out.flush();
+        }
+        match (&token.kind, token.text.as_str()) {
+            (Kind::Punct, "{") => {
+                out.push("{", true);
+                out.flush();
+                out.depth += 1;
+            },
+            (Kind::Punct, "}") => {
+                out.flush();
+                if out.depth == 0 {
+                    return Err("Unmatched '}'".to_string());
+                }
+                out.depth -= 1;
+                out.push_line("}");
+            },
+            (Kind::Punct, "(") => {
+                let end = matching(&tokens, idx)?;
+                print_arguments(&mut out, &tokens[idx + 1..end]);
+                idx = end;
+            },
+            (Kind::Punct, "[") => {
+                let end = matching(&tokens, idx)?;
+                let inner: Vec<&Token> = tokens[idx..=end].iter().filter(|token| token.kind != Kind::Comment).collect();
+                out.push(&render_inline(&inner), space_before(prev, token));
+                idx = end;
+            },
+            (Kind::Punct, ",") => {},
+            (Kind::Str, text) => push_string(&mut out, text),
+            (_, text) => out.push(text, space_before(prev, token)),
+        }
+        prev = Some(&tokens[idx]);
+        idx += 1;
+    }
+    if out.depth > 0 {
+        return Err("Unclosed '{'".to_string());
+    }
+    return Ok(out.finish());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_with_width(source: &str, width: usize) -> String {
+        return format(source, &Layout { indent: "  ".to_string(), width }).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn assert_idempotent(source: &str) {
+        let once = format_with_width(source, 80);
+        assert_eq!(format_with_width(&once, 80), once);
+    }
+
+    #[test]
+    fn selection_sets_and_definitions() {
+        let source = "query Q($id: ID!, $n: Int = 3) { user(id: $id) { ...F name @include(if: true) } }\nfragment F on User { id }";
+        let expected = "query Q($id: ID!, $n: Int = 3) {\n  user(id: $id) {\n    ...F\n    name @include(if: true)\n  }\n}\n\nfragment F on User {\n  id\n}";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn descriptions_and_block_strings() {
+        let source = "\"\"\"\n    A user of the\n      system.\n\"\"\"\ntype User {\n\"The id\"\nid: ID!\n    \"\"\"\n        Shown as\n          typed.\n    \"\"\"\n  name: String # inline\n}";
+        let expected = "\"\"\"\nA user of the\n  system.\n\"\"\"\ntype User {\n  \"The id\"\n  id: ID!\n  \"\"\"\n  Shown as\n    typed.\n  \"\"\"\n  name: String # inline\n}";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn arguments_wrap_at_width() {
+        let source = "type Query { users(first: Int, after: String, orderBy: UserOrder = NAME): [User!]! }";
+        assert_eq!(format_with_width(source, 80), "type Query {\n  users(first: Int, after: String, orderBy: UserOrder = NAME): [User!]!\n}");
+        let expected = "type Query {\n  users(\n    first: Int\n    after: String\n    orderBy: UserOrder = NAME\n  ): [User!]!\n}";
+        assert_eq!(format_with_width(source, 40), expected);
+        assert_eq!(format_with_width(expected, 40), expected);
+    }
+
+    #[test]
+    fn comments_force_wrapped_arguments() {
+        let source = "query {\n  user(\n    # which one\n    id: 1\n  ) { id }\n}";
+        assert_eq!(format_with_width(source, 80), "query {\n  user(\n    # which one\n    id: 1\n  ) {\n    id\n  }\n}");
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn errors() {
+        let layout = Layout { indent: "  ".to_string(), width: 80 };
+        assert!(format("type A { b: String", &layout).is_err());
+        assert!(format("type A { b(c: Int: String }", &layout).is_err());
+        assert!(format("\"\"\"never closed", &layout).is_err());
+    }
+}
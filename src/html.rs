@@ -0,0 +1,407 @@
+use crate::{format, js, utils};
+
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Phrasing content: whitespace between these is significant, so the formatter keeps
+/// them on the line of the surrounding text instead of breaking around them.
+const INLINE_ELEMENTS: [&str; 33] = [
+    "a", "abbr", "b", "bdi", "bdo", "br", "button", "cite", "code", "data", "del", "dfn", "em", "i", "img", "input",
+    "ins", "kbd", "label", "mark", "meter", "output", "progress", "q", "s", "samp", "small", "span", "strong", "sub",
+    "sup", "textarea", "u",
+];
+
+/// Elements whose content is not parsed as HTML and is copied verbatim.
+const RAW_ELEMENTS: [&str; 4] = ["script", "style", "pre", "textarea"];
+
+pub enum Node {
+    /// `<!DOCTYPE ...>` and other `<!...>` or `<?...?>` markup, as written.
+    Doctype(String),
+    /// The whole comment including `<!--` and `-->`.
+    Comment(String),
+    Start { name: String, tag: String, self_closing: bool },
+    End { name: String },
+    Text(String),
+    /// Content of a script, style, pre or textarea element.
+    Raw(String),
+}
+
+fn is_inline(name: &str) -> bool {
+    return INLINE_ELEMENTS.contains(&name);
+}
+
+fn is_void(name: &str) -> bool {
+    return VOID_ELEMENTS.contains(&name);
+}
+
+fn tag_name(tag: &str) -> String {
+    return tag.chars()
+        .take_while(|c| !c.is_whitespace() && *c != '/' && *c != '>')
+        .collect::<String>()
+        .to_ascii_lowercase();
+}
+
+/// Byte length of the tag at the start of `rest`, skipping `>` inside quoted attribute values.
+fn tag_len(rest: &str) -> usize {
+    let mut quote: Option<char> = None;
+    for (idx, c) in rest.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return idx + 1,
+            None => {},
+        }
+    }
+    return rest.len();
+}
+
+/// Collapses whitespace outside quoted attribute values to single spaces.
+fn clean_tag(tag: &str) -> String {
+    let mut result = String::new();
+    let mut quote: Option<char> = None;
+    let mut space = false;
+    for c in tag.chars() {
+        if quote.is_none() && c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space && c != '>' {
+            result.push(' ');
+        }
+        space = false;
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => quote = Some(c),
+            _ => {},
+        }
+        result.push(c);
+    }
+    return result;
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::new();
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            result.push(' ');
+            space = false;
+        }
+        result.push(c);
+    }
+    if space {
+        result.push(' ');
+    }
+    return result;
+}
+
+fn starts_tag(rest: &str, skip: usize) -> bool {
+    return rest[skip..].chars().next().is_some_and(|c| c.is_ascii_alphabetic());
+}
+
+pub fn parse(html: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            let end = rest[4..].find("-->").map_or(rest.len(), |idx| idx + 7);
+            nodes.push(Node::Comment(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map_or(rest.len(), |idx| idx + 1);
+            nodes.push(Node::Doctype(clean_tag(&rest[..end])));
+            rest = &rest[end..];
+        }
+        else if rest.starts_with("</") && starts_tag(rest, 2) {
+            let end = tag_len(rest);
+            nodes.push(Node::End { name: tag_name(&rest[2..end]) });
+            rest = &rest[end..];
+        }
+        else if rest.starts_with('<') && starts_tag(rest, 1) {
+            let end = tag_len(rest);
+            let tag = clean_tag(&rest[..end]);
+            let name = tag_name(&tag[1..]);
+            let self_closing = tag.ends_with("/>");
+            rest = &rest[end..];
+            if RAW_ELEMENTS.contains(&name.as_str()) && !self_closing {
+                let close = rest.to_ascii_lowercase().find(&format!("</{}", name)).unwrap_or(rest.len());
+                nodes.push(Node::Start { name, tag, self_closing });
+                if close > 0 {
+                    nodes.push(Node::Raw(rest[..close].to_string()));
+                }
+                rest = &rest[close..];
+            }
+            else {
+                nodes.push(Node::Start { name, tag, self_closing });
+            }
+        }
+        else {
+            let end = rest[1..].find('<').map_or(rest.len(), |idx| idx + 1);
+            nodes.push(Node::Text(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+    }
+    return nodes;
+}
+
+/// Whether an end tag may be omitted before a new `name` element opens inside `open`.
+fn implicitly_closed(open: &str, name: &str) -> bool {
+    return match open {
+        "p" => !is_inline(name),
+        "li" => name == "li",
+        "dt" | "dd" => name == "dt" || name == "dd",
+        "tr" => name == "tr",
+        "td" | "th" => name == "td" || name == "th" || name == "tr",
+        "option" => name == "option",
+        _ => false,
+    };
+}
+
+struct Formatter<'a> {
+    indent: &'a str,
+    lines: Vec<String>,
+    line: String,
+    open: Vec<String>,
+}
+
+impl Formatter<'_> {
+    fn push_line(&mut self, text: &str) {
+        self.lines.push(format!("{}{}", self.indent.repeat(self.open.len()), text));
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        if self.line.is_empty() {
+            self.line.push_str(text.trim_start());
+        }
+        else {
+            self.line.push_str(text);
+        }
+    }
+
+    fn flush(&mut self) {
+        let line = self.line.trim_end().to_string();
+        if !line.is_empty() {
+            self.push_line(&line);
+        }
+        self.line.clear();
+    }
+
+    /// Re-indents script or style content one level below its tag.
+    fn push_block(&mut self, content: &str) {
+        let lines: Vec<&str> = content.trim_matches('\n').lines().collect();
+        let margin = lines.iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let pad = self.indent.repeat(self.open.len() + 1);
+        for line in lines {
+            let line = line.get(margin..).unwrap_or(line.trim_start()).trim_end();
+            self.lines.push(if line.is_empty() { String::new() } else { format!("{}{}", pad, line) });
+        }
+    }
+}
+
+fn end_tag_optional(name: &str) -> bool {
+    return matches!(name, "p" | "li" | "dt" | "dd" | "tr" | "td" | "th" | "option");
+}
+
+/// Where the element started at `start` ends, if its content is nothing but text and
+/// inline elements, so the whole element fits on one line. The flag tells whether the
+/// end is an explicit end tag or where an omitted one is implied.
+fn inline_content_end(nodes: &[Node], start: usize, name: &str) -> Option<(usize, bool)> {
+    let mut nested = 0;
+    for (idx, node) in nodes.iter().enumerate().skip(start + 1) {
+        match node {
+            Node::Text(_) | Node::Comment(_) => {},
+            Node::Start { name: inner, .. } if nested == 0 && implicitly_closed(name, inner) => return Some((idx, false)),
+            Node::Start { name: inner, .. } if inner == name => nested += 1,
+            Node::Start { name: inner, .. } if is_inline(inner) => {},
+            Node::End { name: inner } if inner == name && nested == 0 => return Some((idx, true)),
+            Node::End { name: inner } if inner == name => nested -= 1,
+            Node::End { name: inner } if is_inline(inner) => {},
+            Node::End { .. } if nested == 0 && end_tag_optional(name) => return Some((idx, false)),
+            Node::Raw(text) if text.trim().is_empty() => {},
+            Node::Raw(_) if matches!(&nodes[idx - 1], Node::Start { name, .. } if name == "textarea") => {},
+            _ => return None,
+        }
+    }
+    if end_tag_optional(name) {
+        return Some((nodes.len(), false));
+    }
+    return None;
+}
+
+/// Puts every block element on its own line, indented by nesting depth. Text, inline
+/// elements and comments between them stay together on one line with runs of
+/// whitespace collapsed, so no whitespace is added or removed where a browser would
+/// render it. Content of `pre` and `textarea` is kept verbatim; scripts and styles are
+/// re-indented.
+pub fn format(html: &str, indent: &str) -> String {
+    let nodes = parse(html);
+    let mut out = Formatter { indent, lines: Vec::new(), line: String::new(), open: Vec::new() };
+    let mut idx = 0;
+    while idx < nodes.len() {
+        match &nodes[idx] {
+            Node::Text(text) => out.push_inline(&collapse_whitespace(text)),
+            Node::Raw(text) => out.line.push_str(text),
+            // A comment in the middle of running text stays there, like the text around it.
+            Node::Comment(text) if !out.line.trim().is_empty() => out.line.push_str(text),
+            Node::Doctype(text) | Node::Comment(text) => {
+                out.flush();
+                out.push_line(text);
+            },
+            Node::Start { name, tag, .. } if is_inline(name) => out.push_inline(tag),
+            Node::End { name } if is_inline(name) => out.line.push_str(&format!("</{}>", name)),
+            Node::Start { name, tag, self_closing } => {
+                out.flush();
+                while out.open.last().is_some_and(|open| implicitly_closed(open, name)) {
+                    out.open.pop();
+                }
+                let raw = match nodes.get(idx + 1) {
+                    Some(Node::Raw(raw)) if RAW_ELEMENTS.contains(&name.as_str()) => Some(raw.as_str()),
+                    _ => None,
+                };
+                let closed_at = if raw.is_some() { idx + 2 } else { idx + 1 };
+                let closed = matches!(nodes.get(closed_at), Some(Node::End { name: end }) if end == name);
+                if *self_closing || is_void(name) {
+                    out.push_line(tag);
+                }
+                else if name == "pre" {
+                    out.push_line(&format!("{}{}{}", tag, raw.unwrap_or(""), if closed { "</pre>" } else { "" }));
+                    idx = if closed { closed_at } else { closed_at - 1 };
+                }
+                else if raw.is_some_and(|raw| !raw.trim().is_empty()) {
+                    out.push_line(tag);
+                    out.push_block(raw.unwrap_or(""));
+                    if closed {
+                        out.push_line(&format!("</{}>", name));
+                    }
+                    idx = if closed { closed_at } else { closed_at - 1 };
+                }
+                else if let Some((end, explicit)) = inline_content_end(&nodes, idx, name) {
+                    let mut inner = Formatter { indent, lines: Vec::new(), line: String::new(), open: Vec::new() };
+                    for node in &nodes[idx + 1..end] {
+                        match node {
+                            Node::Text(text) => inner.push_inline(&collapse_whitespace(text)),
+                            Node::Start { tag, .. } => inner.push_inline(tag),
+                            Node::End { name } => inner.line.push_str(&format!("</{}>", name)),
+                            Node::Raw(text) | Node::Comment(text) => inner.line.push_str(text),
+                            _ => {},
+                        }
+                    }
+                    let close = if explicit { format!("</{}>", name) } else { String::new() };
+                    out.push_line(&format!("{}{}{}", tag, inner.line.trim_end(), close));
+                    idx = if explicit { end } else { end - 1 };
+                }
+                else {
+                    out.push_line(tag);
+                    out.open.push(name.clone());
+                }
+            },
+            Node::End { name } => {
+                out.flush();
+                if let Some(pos) = out.open.iter().rposition(|open| open == name) {
+                    out.open.truncate(pos);
+                }
+                out.push_line(&format!("</{}>", name));
+            },
+        }
+        idx += 1;
+    }
+    out.flush();
+    return out.lines.join("\n");
+}
+
+/// Whether whitespace next to this node can be dropped without changing the rendering.
+fn breaks_flow(node: Option<&Node>) -> bool {
+    return match node {
+        None | Some(Node::Doctype(_)) => true,
+        Some(Node::Start { name, .. }) | Some(Node::End { name }) => !is_inline(name),
+        _ => false,
+    };
+}
+
+/// Conditional comments (`<!--[if IE]>`) are markup for old browsers, not commentary.
+fn is_conditional_comment(comment: &str) -> bool {
+    return comment.starts_with("<!--[if") || comment.starts_with("<!--<![endif]") || comment.starts_with("<!--[endif]");
+}
+
+/// Value of attribute `name` in a cleaned start tag, unquoted.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let start = lower.find(&format!(" {}=", name))? + name.len() + 2;
+    let rest = &tag[start..];
+    let value = match rest.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => rest[1..].split(quote).next().unwrap_or(""),
+        _ => rest.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or(""),
+    };
+    return Some(value.to_string());
+}
+
+/// Minifies inline CSS and JavaScript, leaving other script types (JSON, templates) and
+/// content that fails to parse as written.
+fn minify_raw(tag: &str, name: &str, content: &str, args: &Vec<String>) -> String {
+    if content.trim().is_empty() && (name == "style" || name == "script") {
+        return String::new();
+    }
+    let result = match name {
+        "style" => format::minify_stylesheet(content, args),
+        "script" => match attribute(tag, "type").map(|value| value.to_ascii_lowercase()) {
+            None => js::minify(content, utils::has_option(args, "mangle"), false),
+            Some(kind) if kind.is_empty() || kind == "text/javascript" || kind == "application/javascript" => {
+                js::minify(content, utils::has_option(args, "mangle"), false)
+            },
+            Some(kind) if kind == "module" => js::minify(content, utils::has_option(args, "mangle"), true),
+            Some(_) => return content.to_string(),
+        },
+        _ => return content.to_string(),
+    };
+    return match result {
+        Ok(minified) => minified.trim_end().to_string(),
+        Err(e) => {
+            eprintln!("warning: left inline <{}> unminified: {}", name, e);
+            content.to_string()
+        },
+    };
+}
+
+/// Drops comments, collapses whitespace runs to one space and removes whitespace
+/// next to block elements. `pre` and `textarea` content is kept; inline `<style>` and
+/// `<script>` are minified (`--targets` and `--mangle` apply as for `minify_css` and
+/// `minify_js`).
+pub fn minify(html: &str, args: &Vec<String>) -> String {
+    let nodes: Vec<Node> = parse(html).into_iter()
+        .filter(|node| !matches!(node, Node::Comment(comment) if !is_conditional_comment(comment)))
+        .collect();
+    let mut result = String::new();
+    for (idx, node) in nodes.iter().enumerate() {
+        match node {
+            Node::Doctype(text) | Node::Comment(text) => result.push_str(text),
+            Node::Raw(text) => match &nodes[idx - 1] {
+                Node::Start { name, tag, .. } => result.push_str(&minify_raw(tag, name, text, args)),
+                _ => result.push_str(text),
+            },
+            Node::Start { tag, .. } => result.push_str(tag),
+            Node::End { name } => result.push_str(&format!("</{}>", name)),
+            Node::Text(text) => {
+                let mut text = collapse_whitespace(text);
+                if idx == 0 || breaks_flow(nodes.get(idx - 1)) || result.ends_with(' ') {
+                    text = text.trim_start().to_string();
+                }
+                if breaks_flow(nodes.get(idx + 1)) {
+                    text = text.trim_end().to_string();
+                }
+                result.push_str(&text);
+            },
+        }
+    }
+    return result;
+}
@@ -0,0 +1,1141 @@
+use std::cmp::Ordering;
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Dot,
+    DotDot,
+    /// `.name`, written without a space after the dot.
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    Pipe, Comma, Colon, Semicolon, Question,
+    LParen, RParen, LBracket, RBracket, LBrace, RBrace,
+}
+
+enum Expr {
+    Identity,
+    RecurseAll,
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Iterate(Box<Expr>),
+    Optional(Box<Expr>),
+    Literal(Value),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(Expr, Expr)>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Alternative(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    If(Vec<(Expr, Expr)>, Option<Box<Expr>>),
+    Call(String, Vec<Expr>),
+}
+
+type Outputs = Result<Vec<Value>, String>;
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let is_ident_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '.' && next == Some('.') {
+            tokens.push(Token::DotDot);
+            i += 2;
+        }
+        else if c == '.' && next.is_some_and(is_ident_start) {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && is_ident(chars[i]) { i += 1; }
+            tokens.push(Token::Field(chars[start..i].iter().collect()));
+        }
+        else if c == '.' && !next.is_some_and(|n| n.is_ascii_digit()) {
+            tokens.push(Token::Dot);
+            i += 1;
+        }
+        else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') { i += 1; }
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                i += 1;
+                if i < chars.len() && matches!(chars[i], '+' | '-') { i += 1; }
+                while i < chars.len() && chars[i].is_ascii_digit() { i += 1; }
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(text.parse().map_err(|_| format!("Invalid number '{}'", text))?));
+        }
+        else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("Unterminated string".to_string()),
+                    Some(q) if *q == c => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some('r') => value.push('\r'),
+                            Some(other) => value.push(*other),
+                            None => return Err("Unterminated string".to_string()),
+                        }
+                    },
+                    Some(other) => value.push(*other),
+                }
+                i += 1;
+            }
+            tokens.push(Token::Str(value));
+            i += 1;
+        }
+        else if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident(chars[i]) { i += 1; }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        }
+        else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let op = ["==", "!=", "<=", ">=", "//", "&&", "||"].into_iter().find(|op| *op == two);
+            if let Some(op) = op {
+                tokens.push(match op {
+                    "&&" => Token::Ident("and".to_string()),
+                    "||" => Token::Ident("or".to_string()),
+                    _ => Token::Op(op),
+                });
+                i += 2;
+                continue;
+            }
+            tokens.push(match c {
+                '|' => Token::Pipe,
+                ',' => Token::Comma,
+                ':' => Token::Colon,
+                ';' => Token::Semicolon,
+                '?' => Token::Question,
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                '<' => Token::Op("<"),
+                '>' => Token::Op(">"),
+                '+' => Token::Op("+"),
+                '-' => Token::Op("-"),
+                '*' => Token::Op("*"),
+                '/' => Token::Op("/"),
+                '%' => Token::Op("%"),
+                _ => return Err(format!("Unexpected character '{}'", c)),
+            });
+            i += 1;
+        }
+    }
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.pos);
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        return token;
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        if self.eat(&token) { return Ok(()); }
+        return Err(format!("Expected {:?} but found {:?}", token, self.peek()));
+    }
+
+    fn is_keyword(&self, word: &str) -> bool {
+        return matches!(self.peek(), Some(Token::Ident(w)) if w == word);
+    }
+
+    fn parse_pipe(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_comma()?;
+        while self.eat(&Token::Pipe) {
+            left = Expr::Pipe(Box::new(left), Box::new(self.parse_comma()?));
+        }
+        return Ok(left);
+    }
+
+    fn parse_comma(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_alternative()?;
+        while self.eat(&Token::Comma) {
+            left = Expr::Comma(Box::new(left), Box::new(self.parse_alternative()?));
+        }
+        return Ok(left);
+    }
+
+    fn parse_alternative(&mut self) -> Result<Expr, String> {
+        let left = self.parse_or()?;
+        if self.eat(&Token::Op("//")) {
+            return Ok(Expr::Alternative(Box::new(left), Box::new(self.parse_alternative()?)));
+        }
+        return Ok(left);
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.is_keyword("or") {
+            self.pos += 1;
+            left = Expr::Binary("or", Box::new(left), Box::new(self.parse_and()?));
+        }
+        return Ok(left);
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_comparison()?;
+        while self.is_keyword("and") {
+            self.pos += 1;
+            left = Expr::Binary("and", Box::new(left), Box::new(self.parse_comparison()?));
+        }
+        return Ok(left);
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let left = self.parse_additive()?;
+        if let Some(Token::Op(op @ ("==" | "!=" | "<" | "<=" | ">" | ">="))) = self.peek().cloned() {
+            self.pos += 1;
+            return Ok(Expr::Binary(op, Box::new(left), Box::new(self.parse_additive()?)));
+        }
+        return Ok(left);
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_multiplicative()?;
+        while let Some(Token::Op(op @ ("+" | "-"))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.parse_multiplicative()?));
+        }
+        return Ok(left);
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_postfix()?;
+        while let Some(Token::Op(op @ ("*" | "/" | "%"))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.parse_postfix()?));
+        }
+        return Ok(left);
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    expr = Expr::Field(Box::new(expr), name);
+                },
+                Some(Token::Dot) if matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_))) => {
+                    if let Some(Token::Str(name)) = self.tokens.get(self.pos + 1).cloned() {
+                        self.pos += 2;
+                        expr = Expr::Field(Box::new(expr), name);
+                    }
+                },
+                Some(Token::Dot) if matches!(self.tokens.get(self.pos + 1), Some(Token::LBracket)) => self.pos += 1,
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    expr = self.parse_brackets(expr)?;
+                },
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    expr = Expr::Optional(Box::new(expr));
+                },
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// `[]`, `[index]` or `[from:to]`; the opening bracket has been consumed.
+    fn parse_brackets(&mut self, target: Expr) -> Result<Expr, String> {
+        if self.eat(&Token::RBracket) {
+            return Ok(Expr::Iterate(Box::new(target)));
+        }
+        let from = if self.peek() == Some(&Token::Colon) { None } else { Some(Box::new(self.parse_pipe()?)) };
+        if self.eat(&Token::Colon) {
+            let to = if self.peek() == Some(&Token::RBracket) { None } else { Some(Box::new(self.parse_pipe()?)) };
+            self.expect(Token::RBracket)?;
+            return Ok(Expr::Slice(Box::new(target), from, to));
+        }
+        self.expect(Token::RBracket)?;
+        return Ok(Expr::Index(Box::new(target), from.unwrap()));
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        return match self.next() {
+            Some(Token::Dot) => {
+                if let Some(Token::Str(name)) = self.peek().cloned() {
+                    self.pos += 1;
+                    return Ok(Expr::Field(Box::new(Expr::Identity), name));
+                }
+                Ok(Expr::Identity)
+            },
+            Some(Token::DotDot) => Ok(Expr::RecurseAll),
+            Some(Token::Field(name)) => Ok(Expr::Field(Box::new(Expr::Identity), name)),
+            Some(Token::Num(n)) => Ok(Expr::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Op("-")) => Ok(Expr::Neg(Box::new(self.parse_postfix()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_pipe()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            },
+            Some(Token::LBracket) => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Expr::Array(None));
+                }
+                let expr = self.parse_pipe()?;
+                self.expect(Token::RBracket)?;
+                Ok(Expr::Array(Some(Box::new(expr))))
+            },
+            Some(Token::LBrace) => self.parse_object(),
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                "if" => self.parse_if(),
+                _ => {
+                    let mut args = Vec::new();
+                    if self.eat(&Token::LParen) {
+                        loop {
+                            args.push(self.parse_pipe()?);
+                            if !self.eat(&Token::Semicolon) { break; }
+                        }
+                        self.expect(Token::RParen)?;
+                    }
+                    Ok(Expr::Call(word, args))
+                },
+            },
+            other => Err(format!("Unexpected {:?}", other)),
+        };
+    }
+
+    fn parse_if(&mut self) -> Result<Expr, String> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.parse_pipe()?;
+            if !self.is_keyword("then") { return Err("Expected 'then'".to_string()); }
+            self.pos += 1;
+            branches.push((condition, self.parse_pipe()?));
+            if self.is_keyword("elif") {
+                self.pos += 1;
+                continue;
+            }
+            break;
+        }
+        let otherwise = if self.is_keyword("else") {
+            self.pos += 1;
+            Some(Box::new(self.parse_pipe()?))
+        }
+        else { None };
+        if !self.is_keyword("end") { return Err("Expected 'end'".to_string()); }
+        self.pos += 1;
+        return Ok(Expr::If(branches, otherwise));
+    }
+
+    /// `{a, "b": .x, (.k): .v}`; values bind tighter than `,` as in jq.
+    fn parse_object(&mut self) -> Result<Expr, String> {
+        let mut entries = Vec::new();
+        if self.eat(&Token::RBrace) {
+            return Ok(Expr::Object(entries));
+        }
+        loop {
+            let (key, shorthand) = match self.next() {
+                Some(Token::Ident(name)) | Some(Token::Str(name)) => (Expr::Literal(Value::String(name.clone())), Some(name)),
+                Some(Token::LParen) => {
+                    let key = self.parse_pipe()?;
+                    self.expect(Token::RParen)?;
+                    (key, None)
+                },
+                other => return Err(format!("Unexpected {:?} in object", other)),
+            };
+            let value = if self.eat(&Token::Colon) {
+                self.parse_alternative()?
+            }
+            else {
+                match shorthand {
+                    Some(name) => Expr::Field(Box::new(Expr::Identity), name),
+                    None => return Err("Expected ':' in object".to_string()),
+                }
+            };
+            entries.push((key, value));
+            if self.eat(&Token::RBrace) { break; }
+            self.expect(Token::Comma)?;
+        }
+        return Ok(Expr::Object(entries));
+    }
+}
+
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9.0e15 {
+        return Value::Number(Number::from(n as i64));
+    }
+    return Number::from_f64(n).map_or(Value::Null, Value::Number);
+}
+
+fn type_name(value: &Value) -> &'static str {
+    return match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+}
+
+fn truthy(value: &Value) -> bool {
+    return !matches!(value, Value::Null | Value::Bool(false));
+}
+
+fn as_f64(value: &Value) -> Result<f64, String> {
+    return value.as_f64().ok_or_else(|| format!("{} is not a number", type_name(value)));
+}
+
+/// jq's total order: null < false < true < numbers < strings < arrays < objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    return match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64().unwrap_or(0.0).total_cmp(&y.as_f64().unwrap_or(0.0)),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => {
+            for (l, r) in x.iter().zip(y.iter()) {
+                let ordering = compare(l, r);
+                if ordering != Ordering::Equal { return ordering; }
+            }
+            x.len().cmp(&y.len())
+        },
+        (Value::Object(x), Value::Object(y)) => {
+            let mut x_keys: Vec<&String> = x.keys().collect();
+            let mut y_keys: Vec<&String> = y.keys().collect();
+            x_keys.sort();
+            y_keys.sort();
+            x_keys.cmp(&y_keys).then_with(|| {
+                x_keys.iter().map(|k| compare(&x[*k], &y[*k])).find(|o| *o != Ordering::Equal).unwrap_or(Ordering::Equal)
+            })
+        },
+        _ => rank(a).cmp(&rank(b)),
+    };
+}
+
+fn arithmetic(op: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    return match (op, left, right) {
+        ("+", Value::Null, other) | ("+", other, Value::Null) => Ok(other.clone()),
+        ("+", Value::String(a), Value::String(b)) => Ok(Value::String(format!("{}{}", a, b))),
+        ("+", Value::Array(a), Value::Array(b)) => Ok(Value::Array(a.iter().chain(b.iter()).cloned().collect())),
+        ("+", Value::Object(a), Value::Object(b)) => {
+            let mut merged = a.clone();
+            merged.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+            Ok(Value::Object(merged))
+        },
+        ("-", Value::Array(a), Value::Array(b)) => Ok(Value::Array(a.iter().filter(|v| !b.contains(v)).cloned().collect())),
+        ("/", Value::String(a), Value::String(b)) => Ok(Value::Array(a.split(b.as_str()).map(|s| Value::String(s.to_string())).collect())),
+        (_, Value::Number(_), Value::Number(_)) => {
+            let (a, b) = (as_f64(left)?, as_f64(right)?);
+            match op {
+                "+" => Ok(number(a + b)),
+                "-" => Ok(number(a - b)),
+                "*" => Ok(number(a * b)),
+                "/" if b == 0.0 => Err("Division by zero".to_string()),
+                "/" => Ok(number(a / b)),
+                "%" if b as i64 == 0 => Err("Modulo by zero".to_string()),
+                _ => Ok(number(((a as i64) % (b as i64)) as f64)),
+            }
+        },
+        _ => Err(format!("Cannot apply '{}' to {} and {}", op, type_name(left), type_name(right))),
+    };
+}
+
+fn index(value: &Value, key: &Value) -> Result<Value, String> {
+    return match (value, key) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(map), Value::String(k)) => Ok(map.get(k).cloned().unwrap_or(Value::Null)),
+        (Value::Array(items), Value::Number(_)) => {
+            let idx = as_f64(key)? as i64;
+            let idx = if idx < 0 { items.len() as i64 + idx } else { idx };
+            Ok(if idx < 0 { Value::Null } else { items.get(idx as usize).cloned().unwrap_or(Value::Null) })
+        },
+        _ => Err(format!("Cannot index {} with {}", type_name(value), type_name(key))),
+    };
+}
+
+fn slice_bounds(len: usize, from: Option<f64>, to: Option<f64>) -> (usize, usize) {
+    let clamp = |n: f64| {
+        let n = if n < 0.0 { len as f64 + n } else { n };
+        n.clamp(0.0, len as f64) as usize
+    };
+    let start = from.map_or(0, clamp);
+    let end = to.map_or(len, clamp);
+    return (start, end.max(start));
+}
+
+fn iterate(value: &Value) -> Result<Vec<Value>, String> {
+    return match value {
+        Value::Array(items) => Ok(items.clone()),
+        Value::Object(map) => Ok(map.values().cloned().collect()),
+        _ => Err(format!("Cannot iterate over {}", type_name(value))),
+    };
+}
+
+fn recurse(value: &Value, out: &mut Vec<Value>) {
+    out.push(value.clone());
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| recurse(v, out)),
+        Value::Object(map) => map.values().for_each(|v| recurse(v, out)),
+        _ => {},
+    }
+}
+
+/// Evaluates `expr` and requires exactly one output, for builtin arguments.
+fn single(expr: &Expr, input: &Value) -> Result<Value, String> {
+    let mut outputs = eval(expr, input)?;
+    if outputs.len() != 1 {
+        return Err("Expected a single value as argument".to_string());
+    }
+    return Ok(outputs.remove(0));
+}
+
+fn eval(expr: &Expr, input: &Value) -> Outputs {
+    return match expr {
+        Expr::Identity => Ok(vec![input.clone()]),
+        Expr::RecurseAll => {
+            let mut out = Vec::new();
+            recurse(input, &mut out);
+            Ok(out)
+        },
+        Expr::Field(target, name) => eval(target, input)?.iter().map(|v| match v {
+            Value::Object(_) | Value::Null => index(v, &Value::String(name.clone())),
+            _ => Err(format!("Cannot index {} with \"{}\"", type_name(v), name)),
+        }).collect(),
+        Expr::Index(target, key) => {
+            let mut out = Vec::new();
+            for value in eval(target, input)? {
+                for key in eval(key, input)? {
+                    out.push(index(&value, &key)?);
+                }
+            }
+            Ok(out)
+        },
+        Expr::Slice(target, from, to) => {
+            let from = match from { Some(e) => Some(as_f64(&single(e, input)?)?), None => None };
+            let to = match to { Some(e) => Some(as_f64(&single(e, input)?)?), None => None };
+            eval(target, input)?.iter().map(|value| match value {
+                Value::Array(items) => {
+                    let (start, end) = slice_bounds(items.len(), from, to);
+                    Ok(Value::Array(items[start..end].to_vec()))
+                },
+                Value::String(s) => {
+                    let chars: Vec<char> = s.chars().collect();
+                    let (start, end) = slice_bounds(chars.len(), from, to);
+                    Ok(Value::String(chars[start..end].iter().collect()))
+                },
+                Value::Null => Ok(Value::Null),
+                _ => Err(format!("Cannot slice {}", type_name(value))),
+            }).collect()
+        },
+        Expr::Iterate(target) => {
+            let mut out = Vec::new();
+            for value in eval(target, input)? {
+                out.extend(iterate(&value)?);
+            }
+            Ok(out)
+        },
+        Expr::Optional(inner) => Ok(eval(inner, input).unwrap_or_default()),
+        Expr::Literal(value) => Ok(vec![value.clone()]),
+        Expr::Array(None) => Ok(vec![Value::Array(Vec::new())]),
+        Expr::Array(Some(inner)) => Ok(vec![Value::Array(eval(inner, input)?)]),
+        Expr::Object(entries) => {
+            let mut objects = vec![Map::new()];
+            for (key, value) in entries {
+                let mut next = Vec::new();
+                for k in eval(key, input)? {
+                    let k = match k {
+                        Value::String(s) => s,
+                        other => return Err(format!("Object keys must be strings, not {}", type_name(&other))),
+                    };
+                    for v in eval(value, input)? {
+                        for object in &objects {
+                            let mut object = object.clone();
+                            object.insert(k.clone(), v.clone());
+                            next.push(object);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            Ok(objects.into_iter().map(Value::Object).collect())
+        },
+        Expr::Pipe(left, right) => {
+            let mut out = Vec::new();
+            for value in eval(left, input)? {
+                out.extend(eval(right, &value)?);
+            }
+            Ok(out)
+        },
+        Expr::Comma(left, right) => {
+            let mut out = eval(left, input)?;
+            out.extend(eval(right, input)?);
+            Ok(out)
+        },
+        Expr::Binary(op @ ("and" | "or"), left, right) => {
+            let mut out = Vec::new();
+            for l in eval(left, input)? {
+                if *op == "and" && !truthy(&l) || *op == "or" && truthy(&l) {
+                    out.push(Value::Bool(*op == "or"));
+                    continue;
+                }
+                for r in eval(right, input)? {
+                    out.push(Value::Bool(truthy(&r)));
+                }
+            }
+            Ok(out)
+        },
+        Expr::Binary(op, left, right) => {
+            let mut out = Vec::new();
+            for r in eval(right, input)? {
+                for l in eval(left, input)? {
+                    out.push(match *op {
+                        "==" => Value::Bool(compare(&l, &r) == Ordering::Equal),
+                        "!=" => Value::Bool(compare(&l, &r) != Ordering::Equal),
+                        "<" => Value::Bool(compare(&l, &r) == Ordering::Less),
+                        "<=" => Value::Bool(compare(&l, &r) != Ordering::Greater),
+                        ">" => Value::Bool(compare(&l, &r) == Ordering::Greater),
+                        ">=" => Value::Bool(compare(&l, &r) != Ordering::Less),
+                        _ => arithmetic(op, &l, &r)?,
+                    });
+                }
+            }
+            Ok(out)
+        },
+        Expr::Alternative(left, right) => {
+            let found: Vec<Value> = eval(left, input).unwrap_or_default().into_iter().filter(truthy).collect();
+            if found.is_empty() { eval(right, input) } else { Ok(found) }
+        },
+        Expr::Neg(inner) => eval(inner, input)?.iter().map(|v| Ok(number(-as_f64(v)?))).collect(),
+        Expr::If(branches, otherwise) => eval_if(branches, otherwise, input),
+        Expr::Call(name, args) => call(name, args, input),
+    };
+}
+
+fn eval_if(branches: &[(Expr, Expr)], otherwise: &Option<Box<Expr>>, input: &Value) -> Outputs {
+    let Some(((condition, then), rest)) = branches.split_first() else {
+        return match otherwise {
+            Some(e) => eval(e, input),
+            None => Ok(vec![input.clone()]),
+        };
+    };
+    let mut out = Vec::new();
+    for c in eval(condition, input)? {
+        out.extend(if truthy(&c) { eval(then, input)? } else { eval_if(rest, otherwise, input)? });
+    }
+    return Ok(out);
+}
+
+fn sorted_by(items: Vec<Value>, key: Option<&Expr>) -> Result<Vec<(Value, Value)>, String> {
+    let mut keyed = Vec::new();
+    for item in items {
+        let k = match key {
+            Some(expr) => Value::Array(eval(expr, &item)?),
+            None => item.clone(),
+        };
+        keyed.push((k, item));
+    }
+    keyed.sort_by(|a, b| compare(&a.0, &b.0));
+    return Ok(keyed);
+}
+
+fn as_array<'a>(name: &str, input: &'a Value) -> Result<&'a Vec<Value>, String> {
+    return input.as_array().ok_or_else(|| format!("{} requires an array, got {}", name, type_name(input)));
+}
+
+fn as_str<'a>(name: &str, value: &'a Value) -> Result<&'a str, String> {
+    return value.as_str().ok_or_else(|| format!("{} requires a string, got {}", name, type_name(value)));
+}
+
+fn contains(a: &Value, b: &Value) -> bool {
+    return match (a, b) {
+        (Value::String(x), Value::String(y)) => x.contains(y.as_str()),
+        (Value::Array(x), Value::Array(y)) => y.iter().all(|yv| x.iter().any(|xv| contains(xv, yv))),
+        (Value::Object(x), Value::Object(y)) => y.iter().all(|(k, yv)| x.get(k).is_some_and(|xv| contains(xv, yv))),
+        _ => a == b,
+    };
+}
+
+fn flatten(items: &[Value], depth: usize, out: &mut Vec<Value>) {
+    for item in items {
+        match item {
+            Value::Array(inner) if depth > 0 => flatten(inner, depth - 1, out),
+            _ => out.push(item.clone()),
+        }
+    }
+}
+
+fn call(name: &str, args: &[Expr], input: &Value) -> Outputs {
+    let one = |v: Value| Ok(vec![v]);
+    return match (name, args) {
+        ("empty", []) => Ok(Vec::new()),
+        ("not", []) => one(Value::Bool(!truthy(input))),
+        ("type", []) => one(Value::String(type_name(input).to_string())),
+        ("length", []) => one(match input {
+            Value::Null => number(0.0),
+            Value::Bool(_) => return Err("boolean has no length".to_string()),
+            Value::Number(_) => number(as_f64(input)?.abs()),
+            Value::String(s) => number(s.chars().count() as f64),
+            Value::Array(items) => number(items.len() as f64),
+            Value::Object(map) => number(map.len() as f64),
+        }),
+        ("keys" | "keys_unsorted", []) => one(match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                if name == "keys" { keys.sort(); }
+                Value::Array(keys.into_iter().map(|k| Value::String(k.clone())).collect())
+            },
+            Value::Array(items) => Value::Array((0..items.len()).map(|i| number(i as f64)).collect()),
+            _ => return Err(format!("{} has no keys", type_name(input))),
+        }),
+        ("values", []) => Ok(if input.is_null() { Vec::new() } else { vec![input.clone()] }),
+        ("has", [key]) => one(Value::Bool(match (input, single(key, input)?) {
+            (Value::Object(map), Value::String(k)) => map.contains_key(&k),
+            (Value::Array(items), Value::Number(n)) => n.as_f64().is_some_and(|i| i >= 0.0 && (i as usize) < items.len()),
+            (_, key) => return Err(format!("Cannot check whether {} has a {} key", type_name(input), type_name(&key))),
+        })),
+        ("select", [condition]) => Ok(eval(condition, input)?.iter().filter(|c| truthy(c)).map(|_| input.clone()).collect()),
+        ("map", [f]) => {
+            let mut out = Vec::new();
+            for item in iterate(input)? {
+                out.extend(eval(f, &item)?);
+            }
+            one(Value::Array(out))
+        },
+        ("map_values", [f]) => one(match input {
+            Value::Object(map) => {
+                let mut result = Map::new();
+                for (k, v) in map {
+                    if let Some(first) = eval(f, v)?.into_iter().next() { result.insert(k.clone(), first); }
+                }
+                Value::Object(result)
+            },
+            Value::Array(items) => {
+                let mut result = Vec::new();
+                for v in items {
+                    if let Some(first) = eval(f, v)?.into_iter().next() { result.push(first); }
+                }
+                Value::Array(result)
+            },
+            _ => return Err(format!("Cannot map over {}", type_name(input))),
+        }),
+        ("to_entries", []) => match input {
+            Value::Object(map) => one(Value::Array(map.iter().map(|(k, v)| {
+                let mut entry = Map::new();
+                entry.insert("key".to_string(), Value::String(k.clone()));
+                entry.insert("value".to_string(), v.clone());
+                Value::Object(entry)
+            }).collect())),
+            _ => Err(format!("{} has no entries", type_name(input))),
+        },
+        ("from_entries", []) => {
+            let mut result = Map::new();
+            for entry in as_array(name, input)? {
+                let key = ["key", "k", "name", "Name", "Key"].iter().find_map(|k| entry.get(*k)).cloned().unwrap_or(Value::Null);
+                let value = ["value", "v", "Value"].iter().find_map(|k| entry.get(*k)).cloned().unwrap_or(Value::Null);
+                let key = match key {
+                    Value::String(s) => s,
+                    Value::Null => return Err("from_entries needs a key for every entry".to_string()),
+                    other => other.to_string(),
+                };
+                result.insert(key, value);
+            }
+            one(Value::Object(result))
+        },
+        ("with_entries", [f]) => {
+            let entries = call("to_entries", &[], input)?.remove(0);
+            let mapped = call("map", std::slice::from_ref(f), &entries)?.remove(0);
+            call("from_entries", &[], &mapped)
+        },
+        ("sort", []) => one(Value::Array(sorted_by(as_array(name, input)?.clone(), None)?.into_iter().map(|(_, v)| v).collect())),
+        ("sort_by", [f]) => one(Value::Array(sorted_by(as_array(name, input)?.clone(), Some(f))?.into_iter().map(|(_, v)| v).collect())),
+        ("group_by", [f]) => {
+            let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+            for (key, item) in sorted_by(as_array(name, input)?.clone(), Some(f))? {
+                match groups.last_mut() {
+                    Some((last, group)) if *last == key => group.push(item),
+                    _ => groups.push((key, vec![item])),
+                }
+            }
+            one(Value::Array(groups.into_iter().map(|(_, g)| Value::Array(g)).collect()))
+        },
+        ("unique" | "unique_by", _) => {
+            let mut result: Vec<(Value, Value)> = Vec::new();
+            for (key, item) in sorted_by(as_array(name, input)?.clone(), args.first())? {
+                if result.last().is_none_or(|(last, _)| *last != key) { result.push((key, item)); }
+            }
+            one(Value::Array(result.into_iter().map(|(_, v)| v).collect()))
+        },
+        ("min" | "max", []) => {
+            let items = as_array(name, input)?;
+            let found = if name == "min" { items.iter().min_by(|a, b| compare(a, b)) } else { items.iter().max_by(|a, b| compare(a, b)) };
+            one(found.cloned().unwrap_or(Value::Null))
+        },
+        ("reverse", []) => one(match input {
+            Value::Array(items) => Value::Array(items.iter().rev().cloned().collect()),
+            Value::String(s) => Value::String(s.chars().rev().collect()),
+            Value::Null => Value::Array(Vec::new()),
+            _ => return Err(format!("Cannot reverse {}", type_name(input))),
+        }),
+        ("add", []) => {
+            let mut total = Value::Null;
+            for item in iterate(input)? {
+                total = arithmetic("+", &total, &item)?;
+            }
+            one(total)
+        },
+        ("any" | "all", []) => {
+            let items = as_array(name, input)?;
+            one(Value::Bool(if name == "any" { items.iter().any(truthy) } else { items.iter().all(truthy) }))
+        },
+        ("flatten", _) => {
+            let depth = match args.first() { Some(d) => as_f64(&single(d, input)?)? as usize, None => usize::MAX };
+            let mut out = Vec::new();
+            flatten(as_array(name, input)?, depth, &mut out);
+            one(Value::Array(out))
+        },
+        ("first" | "last", []) => one(match (name, input) {
+            ("first", Value::Array(items)) => items.first().cloned().unwrap_or(Value::Null),
+            (_, Value::Array(items)) => items.last().cloned().unwrap_or(Value::Null),
+            _ => return Err(format!("Cannot take {} of {}", name, type_name(input))),
+        }),
+        ("first", [f]) => Ok(eval(f, input)?.into_iter().take(1).collect()),
+        ("limit", [n, f]) => Ok(eval(f, input)?.into_iter().take(as_f64(&single(n, input)?)? as usize).collect()),
+        ("range", _) => {
+            let bounds: Vec<f64> = args.iter().map(|a| as_f64(&single(a, input)?)).collect::<Result<_, String>>()?;
+            let (start, end) = match bounds.as_slice() {
+                [end] => (0.0, *end),
+                [start, end] => (*start, *end),
+                _ => return Err("range takes one or two arguments".to_string()),
+            };
+            Ok((0..).map(|i| start + i as f64).take_while(|n| *n < end).map(number).collect())
+        },
+        ("contains", [b]) => one(Value::Bool(contains(input, &single(b, input)?))),
+        ("startswith" | "endswith" | "ltrimstr" | "rtrimstr" | "split" | "join" | "test", [arg]) => {
+            let arg = single(arg, input)?;
+            if name == "join" {
+                let parts: Vec<String> = as_array(name, input)?.iter().map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                }).collect();
+                return one(Value::String(parts.join(as_str(name, &arg)?)));
+            }
+            let text = as_str(name, input)?;
+            let arg = as_str(name, &arg)?;
+            one(match name {
+                "startswith" => Value::Bool(text.starts_with(arg)),
+                "endswith" => Value::Bool(text.ends_with(arg)),
+                "ltrimstr" => Value::String(text.strip_prefix(arg).unwrap_or(text).to_string()),
+                "rtrimstr" => Value::String(text.strip_suffix(arg).unwrap_or(text).to_string()),
+                "split" => Value::Array(text.split(arg).map(|s| Value::String(s.to_string())).collect()),
+                _ => Value::Bool(Regex::new(arg).map_err(|e| format!("Invalid regex: {}", e))?.is_match(text)),
+            })
+        },
+        ("ascii_downcase", []) => one(Value::String(as_str(name, input)?.to_ascii_lowercase())),
+        ("ascii_upcase", []) => one(Value::String(as_str(name, input)?.to_ascii_uppercase())),
+        ("tostring", []) => one(Value::String(match input {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })),
+        ("tonumber", []) => one(match input {
+            Value::Number(_) => input.clone(),
+            Value::String(s) => number(s.trim().parse().map_err(|_| format!("Cannot parse '{}' as a number", s))?),
+            _ => return Err(format!("Cannot convert {} to a number", type_name(input))),
+        }),
+        ("tojson", []) => one(Value::String(input.to_string())),
+        ("fromjson", []) => one(serde_json::from_str(as_str(name, input)?).map_err(|e| e.to_string())?),
+        _ => Err(format!("Unknown function {}/{}", name, args.len())),
+    };
+}
+
+/// Translates a JSONPath filter body (`@.price < 10 && @.tags`) into a jq expression.
+/// An `@` inside a string literal (`@.email == 'a@b.c'`) is left as it is.
+fn jsonpath_filter(body: &str) -> Result<Expr, String> {
+    let mut jq = String::new();
+    let chars: Vec<char> = body.chars().collect();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in chars.iter().enumerate() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if *c == '\\' => escaped = true,
+            Some(q) if *c == q => quote = None,
+            Some(_) => {},
+            None if *c == '\'' || *c == '"' => quote = Some(*c),
+            None if *c == '@' => {
+                if !matches!(chars.get(i + 1), Some('.' | '[')) { jq.push('.'); }
+                continue;
+            },
+            None => {},
+        }
+        jq.push(*c);
+    }
+    return parse(&jq);
+}
+
+enum Selector {
+    Name(String),
+    Wildcard,
+    Indices(Vec<i64>),
+    Slice(Option<i64>, Option<i64>),
+    Filter(Expr),
+}
+
+/// Parses `$.a.b[0]['c'][*][1:3][?(@.x > 1)]` and `..` recursive descent.
+fn parse_jsonpath(path: &str) -> Result<Vec<(bool, Selector)>, String> {
+    let chars: Vec<char> = path.trim().chars().collect();
+    if chars.first() != Some(&'$') {
+        return Err("A JSONPath must start with '$'".to_string());
+    }
+    let mut segments = Vec::new();
+    let mut i = 1;
+    while i < chars.len() {
+        let recursive = chars[i] == '.' && chars.get(i + 1) == Some(&'.');
+        if chars[i] == '.' {
+            i += if recursive { 2 } else { 1 };
+            if chars.get(i) != Some(&'[') {
+                let start = i;
+                while i < chars.len() && !matches!(chars[i], '.' | '[') { i += 1; }
+                let name: String = chars[start..i].iter().collect();
+                segments.push((recursive, if name == "*" { Selector::Wildcard } else { Selector::Name(name) }));
+                continue;
+            }
+        }
+        if chars.get(i) != Some(&'[') {
+            return Err(format!("Unexpected '{}' in JSONPath", chars[i]));
+        }
+        let mut depth = 0;
+        let start = i + 1;
+        let mut quote: Option<char> = None;
+        while i < chars.len() {
+            match (quote, chars[i]) {
+                (Some(_), '\\') => i += 1,
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {},
+                (None, '\'' | '"') => quote = Some(chars[i]),
+                (None, '[') => depth += 1,
+                (None, ']') => {
+                    depth -= 1;
+                    if depth == 0 { break; }
+                },
+                _ => {},
+            }
+            i += 1;
+        }
+        if i >= chars.len() {
+            return Err("Unterminated '[' in JSONPath".to_string());
+        }
+        let inner: String = chars[start..i].iter().collect::<String>().trim().to_string();
+        i += 1;
+        let parse_int = |s: &str| s.trim().parse::<i64>().map_err(|_| format!("Invalid index '{}' in JSONPath", s));
+        let selector = if inner == "*" {
+            Selector::Wildcard
+        }
+        else if let Some(body) = inner.strip_prefix("?(").and_then(|s| s.strip_suffix(')')) {
+            Selector::Filter(jsonpath_filter(body)?)
+        }
+        else if inner.starts_with('\'') || inner.starts_with('"') {
+            Selector::Name(inner[1..inner.len().saturating_sub(1)].to_string())
+        }
+        else if let Some((from, to)) = inner.split_once(':') {
+            let bound = |s: &str| if s.trim().is_empty() { Ok(None) } else { parse_int(s).map(Some) };
+            Selector::Slice(bound(from)?, bound(to.split(':').next().unwrap_or(""))?)
+        }
+        else {
+            Selector::Indices(inner.split(',').map(parse_int).collect::<Result<_, String>>()?)
+        };
+        segments.push((recursive, selector));
+    }
+    return Ok(segments);
+}
+
+fn select(node: &Value, selector: &Selector, out: &mut Vec<Value>) -> Result<(), String> {
+    match (selector, node) {
+        (Selector::Name(name), Value::Object(map)) => out.extend(map.get(name).cloned()),
+        (Selector::Wildcard, _) => out.extend(iterate(node).unwrap_or_default()),
+        (Selector::Indices(indices), Value::Array(items)) => {
+            for idx in indices {
+                let idx = if *idx < 0 { items.len() as i64 + idx } else { *idx };
+                if idx >= 0 { out.extend(items.get(idx as usize).cloned()); }
+            }
+        },
+        (Selector::Slice(from, to), Value::Array(items)) => {
+            let (start, end) = slice_bounds(items.len(), from.map(|f| f as f64), to.map(|t| t as f64));
+            out.extend(items[start..end].iter().cloned());
+        },
+        (Selector::Filter(expr), Value::Array(_) | Value::Object(_)) => {
+            for child in iterate(node)? {
+                if eval(expr, &child).unwrap_or_default().iter().any(truthy) {
+                    out.push(child);
+                }
+            }
+        },
+        _ => {},
+    }
+    return Ok(());
+}
+
+fn jsonpath(path: &str, root: &Value) -> Outputs {
+    let mut nodes = vec![root.clone()];
+    for (recursive, selector) in parse_jsonpath(path)? {
+        let mut next = Vec::new();
+        for node in &nodes {
+            if recursive {
+                let mut all = Vec::new();
+                recurse(node, &mut all);
+                for descendant in &all {
+                    select(descendant, &selector, &mut next)?;
+                }
+            }
+            else {
+                select(node, &selector, &mut next)?;
+            }
+        }
+        nodes = next;
+    }
+    return Ok(nodes);
+}
+
+fn parse(query: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(query)?, pos: 0 };
+    if parser.tokens.is_empty() {
+        return Ok(Expr::Identity);
+    }
+    let expr = parser.parse_pipe()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(format!("Unexpected {:?}", parser.tokens[parser.pos]));
+    }
+    return Ok(expr);
+}
+
+/// Runs a JSONPath (when the query starts with `$`) or a jq-style filter, returning
+/// every output value.
+pub fn query(query: &str, input: &Value) -> Outputs {
+    if query.trim_start().starts_with('$') {
+        return jsonpath(query, input);
+    }
+    return eval(&parse(query)?, input);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        return json!({
+            "store": {
+                "books": [
+                    {"title": "Dune", "price": 9.5, "tags": ["sf"], "author": {"email": "frank@example.com"}},
+                    {"title": "Emma", "price": 12, "tags": [], "author": {"email": "jane@example.org"}},
+                    {"title": "Ubik", "price": 7, "author": {"email": "phil@example.com"}}
+                ],
+                "owner": "sam"
+            }
+        });
+    }
+
+    fn run(query_text: &str) -> Vec<Value> {
+        return query(query_text, &sample()).unwrap_or_else(|e| panic!("{}: {}", query_text, e));
+    }
+
+    #[test]
+    fn jsonpath_selectors() {
+        assert_eq!(run("$.store.owner"), vec![json!("sam")]);
+        assert_eq!(run("$['store']['owner']"), vec![json!("sam")]);
+        assert_eq!(run("$.store.books[0].title"), vec![json!("Dune")]);
+        assert_eq!(run("$.store.books[-1].title"), vec![json!("Ubik")]);
+        assert_eq!(run("$.store.books[0,2].title"), vec![json!("Dune"), json!("Ubik")]);
+        assert_eq!(run("$.store.books[1:].title"), vec![json!("Emma"), json!("Ubik")]);
+        assert_eq!(run("$.store.books[*].price"), vec![json!(9.5), json!(12), json!(7)]);
+        assert_eq!(run("$..email").len(), 3);
+        assert_eq!(run("$.store.missing"), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn jsonpath_filters() {
+        assert_eq!(run("$.store.books[?(@.price < 10)].title"), vec![json!("Dune"), json!("Ubik")]);
+        assert_eq!(run("$.store.books[?(@.tags)].title"), vec![json!("Dune"), json!("Emma")]);
+        assert_eq!(run("$.store.books[?(@.price > 8 && @.price < 10)].title"), vec![json!("Dune")]);
+        assert_eq!(run("$.store.books[?(@.author.email == 'jane@example.org')].title"), vec![json!("Emma")]);
+        assert_eq!(run("$.store.books[?(@.title == \"a\\\"@b\")].title"), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn jsonpath_errors() {
+        assert!(query("$.store.books[0", &sample()).is_err());
+        assert!(query("$.store.books[x]", &sample()).is_err());
+    }
+
+    #[test]
+    fn jq_paths_and_pipes() {
+        assert_eq!(run("."), vec![sample()]);
+        assert_eq!(run(".store.owner"), vec![json!("sam")]);
+        assert_eq!(run(".store.books[1].title"), vec![json!("Emma")]);
+        assert_eq!(run(".store.books[].title | length"), vec![json!(4), json!(4), json!(4)]);
+        assert_eq!(run(".store.books[:1][].title"), vec![json!("Dune")]);
+        assert_eq!(run(".store.missing?"), vec![Value::Null]);
+        assert_eq!(run(".store.owner, .store.books[0].price"), vec![json!("sam"), json!(9.5)]);
+    }
+
+    #[test]
+    fn jq_expressions() {
+        assert_eq!(run("[.store.books[] | select(.price < 10) | .title]"), vec![json!(["Dune", "Ubik"])]);
+        assert_eq!(run(".store.books | map(.price) | add"), vec![json!(28.5)]);
+        assert_eq!(run(".store.books | sort_by(.price) | .[0].title"), vec![json!("Ubik")]);
+        assert_eq!(run("{name: .store.owner, count: (.store.books | length)}"), vec![json!({"name": "sam", "count": 3})]);
+        assert_eq!(run(".store.books[2].tags // \"none\""), vec![json!("none")]);
+        assert_eq!(run("if .store.owner == \"sam\" then 1 else 2 end"), vec![json!(1)]);
+        assert_eq!(run(".store | keys"), vec![json!(["books", "owner"])]);
+    }
+
+    #[test]
+    fn jq_errors() {
+        assert!(query(".store.owner | frobnicate", &sample()).is_err());
+        assert!(query("[.store", &sample()).is_err());
+        assert!(query(".store.owner + 1", &sample()).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use oxc::allocator::Allocator;
+use oxc::codegen::{Codegen, CodegenOptions};
+use oxc::mangler::{MangleOptions, Mangler};
+use oxc::parser::Parser;
+use oxc::span::SourceType;
+use crate::validation;
+
+/// Reprints the program without comments or optional whitespace. Working from a full
+/// parse keeps automatic semicolon insertion intact: a line break is replaced by `;`
+/// only where it ended a statement. With `mangle`, local bindings get short names;
+/// top-level names are left alone in scripts, where they are shared globals, but not in
+/// modules.
+pub fn minify(source: &str, mangle: bool, module: bool) -> Result<String, String> {
+    let allocator = Allocator::default();
+    let source_type = if module { SourceType::mjs() } else { SourceType::script() };
+    let parsed = Parser::new(&allocator, source, source_type).parse();
+    if let Some(error) = parsed.diagnostics.errors().next() {
+        let offset = error.labels.first().map_or(0, |label| label.offset());
+        let (line, column) = validation::line_column(source, offset as usize);
+        return Err(format!("line {}, column {}: {}", line, column, error.message));
+    }
+    let scoping = if mangle {
+        Some(Mangler::new().with_options(MangleOptions::default()).build(&parsed.program).scoping)
+    } else {
+        None
+    };
+    let code = Codegen::new()
+        .with_options(CodegenOptions::minify())
+        .with_scoping(scoping)
+        .build(&parsed.program)
+        .code;
+    return Ok(code);
+}
@@ -0,0 +1,561 @@
+use std::fmt;
+
+/// Arrays and objects nested deeper than this are rejected rather than risking a stack
+/// overflow in the recursive parser and printers. serde_json uses the same limit.
+const MAX_DEPTH: usize = 128;
+
+/// A JSON document that remembers how it was written: keys keep their original order,
+/// and numbers and strings keep their source text (so `1.0`, `1e3` and integers wider
+/// than 64 bits survive a round trip untouched).
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    /// The number exactly as written.
+    Number(String),
+    /// The string contents as written, escapes included, without the quotes.
+    String(String),
+    /// Items, then any comments left before the closing bracket.
+    Array(Vec<Member>, Vec<String>),
+    /// Entries, then any comments left before the closing brace.
+    Object(Vec<Member>, Vec<String>),
+}
+
+/// An array item, object entry or the document root, with the comments around it.
+/// `after` holds comments on the same line as the value (or, for the root, after it).
+pub struct Member {
+    pub key: Option<String>,
+    pub value: JsonValue,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+pub struct JsonError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} at line {} column {}", self.message, self.line, self.column);
+    }
+}
+
+pub struct JsonFormat {
+    pub indent: String,
+    pub sort_keys: bool,
+    pub compact_arrays: bool,
+    pub ascii: bool,
+    pub comments: bool,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Accept JSONC and JSON5: comments, trailing commas, unquoted keys,
+    /// single-quoted strings and the extra number forms.
+    lenient: bool,
+    /// Arrays and objects currently open.
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> JsonError {
+        let before = &self.chars[..self.pos.min(self.chars.len())];
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+        return JsonError { message: message.to_string(), line, column };
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.pos).copied();
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        return self.chars.get(self.pos + offset).copied();
+    }
+
+    fn is_whitespace(&self, c: char, newlines: bool) -> bool {
+        if matches!(c, ' ' | '\t') { return true; }
+        if matches!(c, '\n' | '\r') { return newlines; }
+        return self.lenient && matches!(c, '\u{0b}' | '\u{0c}' | '\u{a0}' | '\u{feff}')
+            || self.lenient && newlines && matches!(c, '\u{2028}' | '\u{2029}');
+    }
+
+    fn at_comment(&self) -> bool {
+        return self.lenient && self.peek() == Some('/') && matches!(self.peek_at(1), Some('/' | '*'));
+    }
+
+    fn read_comment(&mut self) -> Result<String, JsonError> {
+        let start = self.pos;
+        if self.peek_at(1) == Some('/') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+            return Ok(self.chars[start..self.pos].iter().collect::<String>().trim_end().to_string());
+        }
+        self.pos += 2;
+        while !(self.peek() == Some('*') && self.peek_at(1) == Some('/')) {
+            if self.peek().is_none() {
+                self.pos = start;
+                return Err(self.error("Unterminated block comment"));
+            }
+            self.pos += 1;
+        }
+        self.pos += 2;
+        return Ok(self.chars[start..self.pos].iter().collect());
+    }
+
+    /// Skips whitespace, returning the comments passed on the way.
+    fn skip_whitespace(&mut self) -> Result<Vec<String>, JsonError> {
+        let mut comments = Vec::new();
+        loop {
+            match self.peek() {
+                Some(c) if self.is_whitespace(c, true) => self.pos += 1,
+                Some('/') if self.at_comment() => comments.push(self.read_comment()?),
+                _ => return Ok(comments),
+            }
+        }
+    }
+
+    /// A comment following a value on the same line.
+    fn inline_comment(&mut self) -> Result<Option<String>, JsonError> {
+        while matches!(self.peek(), Some(c) if self.is_whitespace(c, false)) {
+            self.pos += 1;
+        }
+        if self.at_comment() {
+            return Ok(Some(self.read_comment()?));
+        }
+        return Ok(None);
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("Expected '{}'", expected)));
+        }
+        self.pos += 1;
+        return Ok(());
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        return match self.peek() {
+            Some('{') => {
+                let (members, closing) = self.parse_nested('}', true)?;
+                Ok(JsonValue::Object(members, closing))
+            },
+            Some('[') => {
+                let (members, closing) = self.parse_nested(']', false)?;
+                Ok(JsonValue::Array(members, closing))
+            },
+            Some('"') => Ok(JsonValue::String(self.parse_string('"')?)),
+            Some('\'') if self.lenient => Ok(JsonValue::String(self.parse_string('\'')?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('+' | '.') if self.lenient => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() => self.parse_literal(),
+            Some(c) => Err(self.error(&format!("Unexpected character '{}'", c))),
+            None => Err(self.error("Unexpected end of input")),
+        };
+    }
+
+    fn parse_key(&mut self) -> Result<String, JsonError> {
+        return match self.peek() {
+            Some('"') => self.parse_string('"'),
+            Some('\'') if self.lenient => self.parse_string('\''),
+            Some(c) if self.lenient && (c.is_alphabetic() || c == '_' || c == '$') => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_' || c == '$') {
+                    self.pos += 1;
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            },
+            _ => Err(self.error("Expected a string key")),
+        };
+    }
+
+    fn parse_nested(&mut self, close: char, keyed: bool) -> Result<(Vec<Member>, Vec<String>), JsonError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(&format!("Nesting is deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let result = self.parse_members(close, keyed);
+        self.depth -= 1;
+        return result;
+    }
+
+    /// Parses an array or object; the opening bracket is at the current position.
+    fn parse_members(&mut self, close: char, keyed: bool) -> Result<(Vec<Member>, Vec<String>), JsonError> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        let mut before = self.skip_whitespace()?;
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok((members, before));
+        }
+        loop {
+            let key = if keyed {
+                let key = self.parse_key()?;
+                before.extend(self.skip_whitespace()?);
+                self.expect(':')?;
+                before.extend(self.skip_whitespace()?);
+                Some(key)
+            }
+            else { None };
+            let value = self.parse_value()?;
+            let mut after: Vec<String> = self.inline_comment()?.into_iter().collect();
+            let pending = self.skip_whitespace()?;
+            match self.peek() {
+                Some(',') => {
+                    self.pos += 1;
+                    if after.is_empty() {
+                        after.extend(self.inline_comment()?);
+                    }
+                    members.push(Member { key, value, before, after });
+                    before = pending;
+                    before.extend(self.skip_whitespace()?);
+                    if self.peek() == Some(close) {
+                        if !self.lenient {
+                            return Err(self.error("Trailing comma"));
+                        }
+                        self.pos += 1;
+                        return Ok((members, before));
+                    }
+                },
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    members.push(Member { key, value, before, after });
+                    return Ok((members, pending));
+                },
+                _ => return Err(self.error(&format!("Expected ',' or '{}'", close))),
+            }
+        }
+    }
+
+    /// Returns the contents in JSON form: strict input is kept as written, while
+    /// JSON5-only escapes and quotes are rewritten to their JSON equivalents.
+    fn parse_string(&mut self, quote: char) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut raw = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated string")),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(raw);
+                },
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(c @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => {
+                            raw.push('\\');
+                            raw.push(c);
+                            self.pos += 1;
+                        },
+                        Some('u') => {
+                            raw.push_str("\\u");
+                            self.pos += 1;
+                            for _ in 0..4 {
+                                match self.peek() {
+                                    Some(c) if c.is_ascii_hexdigit() => {
+                                        raw.push(c);
+                                        self.pos += 1;
+                                    },
+                                    _ => return Err(self.error("Invalid \\u escape")),
+                                }
+                            }
+                        },
+                        Some('x') if self.lenient => {
+                            let digits: String = self.chars.iter().skip(self.pos + 1).take(2).collect();
+                            if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                                return Err(self.error("Invalid \\x escape"));
+                            }
+                            raw.push_str(&format!("\\u00{}", digits));
+                            self.pos += 3;
+                        },
+                        Some('\r') if self.lenient => {
+                            self.pos += 1;
+                            if self.peek() == Some('\n') { self.pos += 1; }
+                        },
+                        Some('\n' | '\u{2028}' | '\u{2029}') if self.lenient => self.pos += 1,
+                        Some(c) if self.lenient && (!c.is_ascii_digit() || c == '0' && !matches!(self.peek_at(1), Some('0'..='9'))) => {
+                            self.pos += 1;
+                            push_json_char(&mut raw, match c {
+                                'v' => '\u{0b}',
+                                '0' => '\0',
+                                _ => c,
+                            });
+                        },
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    }
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(self.error("Control character in string")),
+                Some(c) => {
+                    push_json_char(&mut raw, c);
+                    self.pos += 1;
+                },
+            }
+        }
+    }
+
+    fn take_digits(&mut self, radix: u32) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_digit(radix)) {
+            self.pos += 1;
+        }
+        return self.chars[start..self.pos].iter().collect();
+    }
+
+    /// JSON5 numbers (`+1`, `.5`, `5.`, `0xFF`) are rewritten in JSON form.
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let mut number = String::new();
+        match self.peek() {
+            Some('-') => {
+                number.push('-');
+                self.pos += 1;
+            },
+            Some('+') => self.pos += 1,
+            _ => {},
+        }
+        if self.lenient && matches!(self.peek(), Some('I' | 'N')) {
+            return self.parse_literal();
+        }
+        if self.lenient && self.peek() == Some('0') && matches!(self.peek_at(1), Some('x' | 'X')) {
+            self.pos += 2;
+            let digits = self.take_digits(16);
+            return match u128::from_str_radix(&digits, 16) {
+                Ok(value) => Ok(JsonValue::Number(format!("{}{}", number, value))),
+                Err(_) => Err(self.error("Invalid hexadecimal number")),
+            };
+        }
+        let integer = self.take_digits(10);
+        if integer.is_empty() && !(self.lenient && self.peek() == Some('.')) {
+            return Err(self.error("Invalid number"));
+        }
+        if integer.len() > 1 && integer.starts_with('0') {
+            return Err(self.error("Leading zeros are not allowed"));
+        }
+        number.push_str(if integer.is_empty() { "0" } else { &integer });
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            let fraction = self.take_digits(10);
+            if fraction.is_empty() && (!self.lenient || integer.is_empty()) {
+                return Err(self.error("Expected digits after '.'"));
+            }
+            if !fraction.is_empty() {
+                number.push('.');
+                number.push_str(&fraction);
+            }
+        }
+        if let Some(e @ ('e' | 'E')) = self.peek() {
+            number.push(e);
+            self.pos += 1;
+            if let Some(sign @ ('+' | '-')) = self.peek() {
+                number.push(sign);
+                self.pos += 1;
+            }
+            let exponent = self.take_digits(10);
+            if exponent.is_empty() { return Err(self.error("Expected digits in exponent")); }
+            number.push_str(&exponent);
+        }
+        return Ok(JsonValue::Number(number));
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let word: String = self.chars[start..self.pos].iter().collect();
+        return match word.as_str() {
+            "null" => Ok(JsonValue::Null),
+            "true" => Ok(JsonValue::Bool(true)),
+            "false" => Ok(JsonValue::Bool(false)),
+            "Infinity" | "NaN" if self.lenient => {
+                self.pos = start;
+                Err(self.error(&format!("{} has no JSON equivalent", word)))
+            },
+            _ => {
+                self.pos = start;
+                Err(self.error(&format!("Unexpected token '{}'", word)))
+            },
+        };
+    }
+
+    fn parse_document(&mut self) -> Result<Member, JsonError> {
+        let before = self.skip_whitespace()?;
+        let value = self.parse_value()?;
+        let after = self.skip_whitespace()?;
+        if self.pos < self.chars.len() {
+            return Err(self.error("Unexpected trailing characters"));
+        }
+        return Ok(Member { key: None, value, before, after });
+    }
+}
+
+fn push_json_char(raw: &mut String, c: char) {
+    match c {
+        '"' => raw.push_str("\\\""),
+        '\\' => raw.push_str("\\\\"),
+        '\n' => raw.push_str("\\n"),
+        '\r' => raw.push_str("\\r"),
+        '\t' => raw.push_str("\\t"),
+        c if (c as u32) < 0x20 => raw.push_str(&format!("\\u{:04x}", c as u32)),
+        c => raw.push(c),
+    }
+}
+
+/// Strict RFC 8259 JSON.
+pub fn parse(text: &str) -> Result<Member, JsonError> {
+    return Parser { chars: text.chars().collect(), pos: 0, lenient: false, depth: 0 }.parse_document();
+}
+
+/// JSON, JSONC or JSON5; comments are kept on the returned tree.
+pub fn parse_lenient(text: &str) -> Result<Member, JsonError> {
+    return Parser { chars: text.chars().collect(), pos: 0, lenient: true, depth: 0 }.parse_document();
+}
+
+fn is_scalar(member: &Member) -> bool {
+    return !matches!(member.value, JsonValue::Array(..) | JsonValue::Object(..));
+}
+
+fn has_comments(member: &Member) -> bool {
+    return !member.before.is_empty() || !member.after.is_empty();
+}
+
+/// Characters outside ASCII become `\uXXXX` escapes, using surrogate pairs where needed.
+fn write_string(raw: &str, ascii: bool, out: &mut String) {
+    out.push('"');
+    if !ascii {
+        out.push_str(raw);
+    }
+    else {
+        for c in raw.chars() {
+            if c.is_ascii() {
+                out.push(c);
+                continue;
+            }
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                out.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    out.push('"');
+}
+
+fn ordered(members: &[Member], sort_keys: bool) -> Vec<&Member> {
+    let mut result: Vec<&Member> = members.iter().collect();
+    if sort_keys {
+        result.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+    return result;
+}
+
+fn write_comment_lines(comments: &[String], pad: &str, out: &mut String) {
+    for comment in comments {
+        out.push_str(pad);
+        out.push_str(comment);
+        out.push('\n');
+    }
+}
+
+fn write_members(members: &[Member], closing: &[String], brackets: (char, char), format: &JsonFormat, depth: usize, out: &mut String) {
+    let pad = format.indent.repeat(depth + 1);
+    let closing = if format.comments { closing } else { &[] };
+    if members.is_empty() && closing.is_empty() {
+        out.push(brackets.0);
+        out.push(brackets.1);
+        return;
+    }
+    let compact = brackets.0 == '['
+        && format.compact_arrays
+        && members.iter().all(|m| is_scalar(m) && !(format.comments && has_comments(m)))
+        && closing.is_empty();
+    out.push(brackets.0);
+    if compact {
+        for (idx, member) in members.iter().enumerate() {
+            if idx > 0 { out.push_str(", "); }
+            write_pretty(&member.value, format, depth, out);
+        }
+        out.push(brackets.1);
+        return;
+    }
+    out.push('\n');
+    let members = ordered(members, format.sort_keys);
+    for (idx, member) in members.iter().enumerate() {
+        if format.comments {
+            write_comment_lines(&member.before, &pad, out);
+        }
+        out.push_str(&pad);
+        if let Some(key) = &member.key {
+            write_string(key, format.ascii, out);
+            out.push_str(": ");
+        }
+        write_pretty(&member.value, format, depth + 1, out);
+        if idx + 1 < members.len() { out.push(','); }
+        if format.comments && !member.after.is_empty() {
+            out.push(' ');
+            out.push_str(&member.after.join(" "));
+        }
+        out.push('\n');
+    }
+    write_comment_lines(closing, &pad, out);
+    out.push_str(&format.indent.repeat(depth));
+    out.push(brackets.1);
+}
+
+fn write_pretty(value: &JsonValue, format: &JsonFormat, depth: usize, out: &mut String) {
+    match value {
+        JsonValue::Array(members, closing) => write_members(members, closing, ('[', ']'), format, depth, out),
+        JsonValue::Object(members, closing) => write_members(members, closing, ('{', '}'), format, depth, out),
+        _ => write_compact(value, format, out),
+    }
+}
+
+fn write_compact(value: &JsonValue, format: &JsonFormat, out: &mut String) {
+    match value {
+        JsonValue::Null => out.push_str("null"),
+        JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => out.push_str(n),
+        JsonValue::String(s) => write_string(s, format.ascii, out),
+        JsonValue::Array(members, _) => {
+            out.push('[');
+            for (idx, member) in members.iter().enumerate() {
+                if idx > 0 { out.push(','); }
+                write_compact(&member.value, format, out);
+            }
+            out.push(']');
+        },
+        JsonValue::Object(members, _) => {
+            out.push('{');
+            for (idx, member) in ordered(members, format.sort_keys).into_iter().enumerate() {
+                if idx > 0 { out.push(','); }
+                write_string(member.key.as_deref().unwrap_or_default(), format.ascii, out);
+                out.push(':');
+                write_compact(&member.value, format, out);
+            }
+            out.push('}');
+        },
+    }
+}
+
+/// Comments are only written when `format.comments` is set, which makes the output JSONC.
+pub fn to_pretty(document: &Member, format: &JsonFormat) -> String {
+    let mut out = String::new();
+    if format.comments {
+        write_comment_lines(&document.before, "", &mut out);
+    }
+    write_pretty(&document.value, format, 0, &mut out);
+    if format.comments {
+        for comment in &document.after {
+            out.push('\n');
+            out.push_str(comment);
+        }
+    }
+    return out;
+}
+
+pub fn to_compact(document: &Member, format: &JsonFormat) -> String {
+    let mut out = String::new();
+    write_compact(&document.value, format, &mut out);
+    return out;
+}
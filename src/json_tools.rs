@@ -0,0 +1,301 @@
+use std::fs;
+use serde_json::{json, Map, Value};
+use crate::{jq, utils};
+
+pub enum JsonToolOp {
+    JsonQuery, JsonDiff, JsonPatch, JsonMergePatch, Unknown,
+}
+
+fn str_to_json_tool_op(arg: &str) -> JsonToolOp {
+    return match arg {
+        "json_query" => JsonToolOp::JsonQuery,
+        "json_diff" => JsonToolOp::JsonDiff,
+        "json_patch" => JsonToolOp::JsonPatch,
+        "json_merge_patch" => JsonToolOp::JsonMergePatch,
+        _ => JsonToolOp::Unknown,
+    }
+}
+
+pub fn select_json_tool_option(args: &Vec<String>) -> JsonToolOp {
+    let json_tool_options: Vec<String> = vec!["json_query", "json_diff", "json_patch", "json_merge_patch"].into_iter().map(|x| x.to_string()).collect();
+    for arg in args {
+        if json_tool_options.contains(&arg.to_lowercase()) {
+            return str_to_json_tool_op(arg);
+        }
+    }
+    return JsonToolOp::Unknown
+}
+
+pub fn handle_json_tool_operation(text: &str, op: JsonToolOp, args: &Vec<String>) -> String {
+    return match op {
+        JsonToolOp::JsonQuery => json_query(text, args),
+        JsonToolOp::JsonDiff => json_diff(text, args),
+        JsonToolOp::JsonPatch => json_patch(text, args),
+        JsonToolOp::JsonMergePatch => json_merge_patch(text, args),
+        JsonToolOp::Unknown => panic!("Unknown JSON operation specified."),
+    };
+}
+
+fn read_json(text: &str, op: &str) -> Value {
+    return match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => panic!("Unable to read JSON data ({}): {}", op, e),
+    };
+}
+
+/// `--query` takes a JSONPath (`$.items[?(@.age > 30)].name`) or a jq-style filter
+/// (`.items[] | select(.age > 30) | .name`). Each result is printed as pretty JSON, or
+/// with `--raw` one per line with strings unquoted.
+fn json_query(text: &str, args: &Vec<String>) -> String {
+    let query = utils::get_option_value(args, "query").unwrap_or_else(|| ".".to_string());
+    let results = match jq::query(&query, &read_json(text, "json_query")) {
+        Ok(results) => results,
+        Err(e) => panic!("Unable to run query '{}': {}", query, e),
+    };
+    let raw = utils::has_option(args, "raw");
+    return results.iter().map(|value| match value {
+        Value::String(s) if raw => s.clone(),
+        _ if raw => value.to_string(),
+        _ => serde_json::to_string_pretty(value).unwrap(),
+    }).collect::<Vec<String>>().join("\n");
+}
+
+/// The documents a multi-input operation works on: the `-s` string, if given, followed
+/// by every file named on the command line.
+fn read_inputs(text: &str, args: &Vec<String>, op: &str) -> Vec<Value> {
+    let mut inputs = Vec::new();
+    if utils::flags_contains(&utils::get_flags(args), 's') {
+        inputs.push(read_json(text, op));
+    }
+    for path in utils::find_file_paths(args) {
+        let contents = fs::read_to_string(&path).unwrap_or_else(|e| panic!("Unable to read {}: {}", path, e));
+        inputs.push(read_json(&contents, op));
+    }
+    return inputs;
+}
+
+fn two_inputs(text: &str, args: &Vec<String>, op: &str, usage: &str) -> (Value, Value) {
+    let mut inputs = read_inputs(text, args, op);
+    if inputs.len() != 2 {
+        panic!("{} needs exactly two inputs: bdp {} {}", op, op, usage);
+    }
+    let second = inputs.pop().unwrap();
+    return (inputs.pop().unwrap(), second);
+}
+
+/// RFC 6901 escaping of one reference token.
+fn pointer_token(key: &str) -> String {
+    return key.replace('~', "~0").replace('/', "~1");
+}
+
+fn unescape_pointer_token(token: &str) -> String {
+    return token.replace("~1", "/").replace("~0", "~");
+}
+
+/// Produces RFC 6902 operations turning `a` into `b`. Objects are compared key by key
+/// and arrays index by index, with surplus items removed from the end first so the
+/// operations apply in order.
+fn diff(a: &Value, b: &Value, path: &str, ops: &mut Vec<Value>) {
+    match (a, b) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, value) in left {
+                let child = format!("{}/{}", path, pointer_token(key));
+                match right.get(key) {
+                    Some(other) => diff(value, other, &child, ops),
+                    None => ops.push(json!({"op": "remove", "path": child})),
+                }
+            }
+            for (key, value) in right {
+                if !left.contains_key(key) {
+                    ops.push(json!({"op": "add", "path": format!("{}/{}", path, pointer_token(key)), "value": value}));
+                }
+            }
+        },
+        (Value::Array(left), Value::Array(right)) => {
+            let common = left.len().min(right.len());
+            for idx in 0..common {
+                diff(&left[idx], &right[idx], &format!("{}/{}", path, idx), ops);
+            }
+            for idx in (common..left.len()).rev() {
+                ops.push(json!({"op": "remove", "path": format!("{}/{}", path, idx)}));
+            }
+            for (idx, value) in right.iter().enumerate().skip(common) {
+                ops.push(json!({"op": "add", "path": format!("{}/{}", path, idx), "value": value}));
+            }
+        },
+        _ if a != b => ops.push(json!({"op": "replace", "path": path, "value": b})),
+        _ => {},
+    }
+}
+
+/// Lines such as `~ /a/b: 1 -> 2`, `+ /c: {...}` and `- /d: "old"`.
+fn describe_diff(a: &Value, ops: &[Value]) -> String {
+    if ops.is_empty() {
+        return "No differences.".to_string();
+    }
+    let mut lines = Vec::new();
+    let mut state = a.clone();
+    for op in ops {
+        let path = op["path"].as_str().unwrap_or("");
+        let shown = if path.is_empty() { "(root)" } else { path };
+        let old = state.pointer(path).cloned();
+        lines.push(match op["op"].as_str() {
+            Some("add") => format!("+ {}: {}", shown, op["value"]),
+            Some("remove") => format!("- {}: {}", shown, old.unwrap_or(Value::Null)),
+            _ => format!("~ {}: {} -> {}", shown, old.unwrap_or(Value::Null), op["value"]),
+        });
+        if let Err(e) = apply_operation(&mut state, op) {
+            panic!("Unable to describe JSON diff: {}", e);
+        }
+    }
+    return lines.join("\n");
+}
+
+/// Human-readable by default; `--json-patch` prints the RFC 6902 patch instead,
+/// which `json_patch` can apply.
+fn json_diff(text: &str, args: &Vec<String>) -> String {
+    let (a, b) = two_inputs(text, args, "json_diff", "<old.json> <new.json>");
+    let mut ops = Vec::new();
+    diff(&a, &b, "", &mut ops);
+    if utils::has_option(args, "json-patch") {
+        return serde_json::to_string_pretty(&Value::Array(ops)).unwrap();
+    }
+    return describe_diff(&a, &ops);
+}
+
+/// Splits a pointer into the parent pointer and the final, unescaped token.
+fn split_pointer(path: &str) -> Result<(&str, String), String> {
+    return match path.rfind('/') {
+        Some(idx) => Ok((&path[..idx], unescape_pointer_token(&path[idx + 1..]))),
+        None => Err(format!("Invalid JSON Pointer '{}'", path)),
+    };
+}
+
+fn array_index(token: &str, len: usize, allow_end: bool) -> Result<usize, String> {
+    if allow_end && token == "-" {
+        return Ok(len);
+    }
+    let idx: usize = token.parse().map_err(|_| format!("Invalid array index '{}'", token))?;
+    if idx > len || (!allow_end && idx == len) {
+        return Err(format!("Array index {} is out of bounds", idx));
+    }
+    return Ok(idx);
+}
+
+fn add_value(document: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    if path.is_empty() {
+        *document = value;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    return match document.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, value);
+            Ok(())
+        },
+        Some(Value::Array(items)) => {
+            let idx = array_index(&token, items.len(), true)?;
+            items.insert(idx, value);
+            Ok(())
+        },
+        Some(_) => Err(format!("Cannot add to '{}': parent is not a container", path)),
+        None => Err(format!("Path '{}' does not exist", parent)),
+    };
+}
+
+fn remove_value(document: &mut Value, path: &str) -> Result<Value, String> {
+    let (parent, token) = split_pointer(path)?;
+    return match document.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.shift_remove(&token).ok_or_else(|| format!("Path '{}' does not exist", path)),
+        Some(Value::Array(items)) => {
+            let idx = array_index(&token, items.len(), false)?;
+            Ok(items.remove(idx))
+        },
+        _ => Err(format!("Path '{}' does not exist", path)),
+    };
+}
+
+fn apply_operation(document: &mut Value, op: &Value) -> Result<(), String> {
+    let path = op["path"].as_str().ok_or("Every operation needs a string 'path'")?;
+    let value = || op.get("value").cloned().ok_or_else(|| format!("'{}' needs a 'value'", op["op"]));
+    let from = || op["from"].as_str().ok_or_else(|| format!("'{}' needs a string 'from'", op["op"]));
+    return match op["op"].as_str() {
+        Some("add") => add_value(document, path, value()?),
+        Some("remove") => remove_value(document, path).map(|_| ()),
+        Some("replace") => {
+            let target = document.pointer_mut(path).ok_or_else(|| format!("Path '{}' does not exist", path))?;
+            *target = value()?;
+            Ok(())
+        },
+        Some("move") => {
+            let from = from()?;
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(format!("Cannot move '{}' into its own child '{}'", from, path));
+            }
+            let moved = remove_value(document, from)?;
+            add_value(document, path, moved)
+        },
+        Some("copy") => {
+            let from = from()?;
+            let copied = document.pointer(from).cloned().ok_or_else(|| format!("Path '{}' does not exist", from))?;
+            add_value(document, path, copied)
+        },
+        Some("test") => match document.pointer(path) {
+            Some(actual) if *actual == value()? => Ok(()),
+            Some(actual) => Err(format!("Test failed at '{}': found {}", path, actual)),
+            None => Err(format!("Test failed: path '{}' does not exist", path)),
+        },
+        other => Err(format!("Unknown patch operation {:?}", other)),
+    };
+}
+
+/// Applies an RFC 6902 patch (the second input) to a document (the first). The patch is
+/// atomic: if any operation fails nothing is printed but the error.
+fn json_patch(text: &str, args: &Vec<String>) -> String {
+    let (mut document, patch) = two_inputs(text, args, "json_patch", "<document.json> <patch.json>");
+    let ops = match patch {
+        Value::Array(ops) => ops,
+        _ => panic!("A JSON Patch must be an array of operations."),
+    };
+    for (idx, op) in ops.iter().enumerate() {
+        if let Err(e) = apply_operation(&mut document, op) {
+            panic!("Unable to apply operation {} of the JSON patch: {}", idx, e);
+        }
+    }
+    return serde_json::to_string_pretty(&document).unwrap();
+}
+
+/// RFC 7396: objects merge recursively, `null` deletes a key, anything else replaces.
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.shift_remove(key);
+            }
+            else {
+                merge_patch(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Merges every following input into the first, in order, so several layers of
+/// configuration can be combined at once.
+fn json_merge_patch(text: &str, args: &Vec<String>) -> String {
+    let mut inputs = read_inputs(text, args, "json_merge_patch").into_iter();
+    let mut document = match inputs.next() {
+        Some(document) => document,
+        None => panic!("json_merge_patch needs a document and at least one patch."),
+    };
+    for patch in inputs {
+        merge_patch(&mut document, &patch);
+    }
+    return serde_json::to_string_pretty(&document).unwrap();
+}
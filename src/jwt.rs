@@ -0,0 +1,132 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use p256::pkcs8::DecodePublicKey;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::signature::Verifier;
+use serde_json::Value;
+use sha2::{Sha256, Sha384, Sha512};
+
+/// What to check the signature against: an HMAC secret or a PEM public key.
+pub enum JwtKey {
+    Secret(String), Pem(String), None,
+}
+
+struct Jwt {
+    header: Value,
+    payload: Value,
+    signing_input: String,
+    signature: Vec<u8>,
+}
+
+/// JWTs use unpadded base64url, but some tools emit padding anyway.
+fn decode_segment(segment: &str) -> Result<Vec<u8>, String> {
+    return URL_SAFE_NO_PAD
+        .decode(segment.trim_end_matches('='))
+        .map_err(|e| format!("Invalid base64url segment: {}", e));
+}
+
+fn decode_json_segment(segment: &str, name: &str) -> Result<Value, String> {
+    let bytes = decode_segment(segment)?;
+    return serde_json::from_slice(&bytes).map_err(|e| format!("The {} is not valid JSON: {}", name, e));
+}
+
+fn parse_jwt(token: &str) -> Result<Jwt, String> {
+    let token = token.trim().trim_start_matches("Bearer ").trim();
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(format!("Expected 3 dot-separated segments, found {}.", parts.len()));
+    }
+    return Ok(Jwt {
+        header: decode_json_segment(parts[0], "header")?,
+        payload: decode_json_segment(parts[1], "payload")?,
+        signing_input: format!("{}.{}", parts[0], parts[1]),
+        signature: decode_segment(parts[2])?,
+    });
+}
+
+fn describe_timestamp(claim: &str, value: &Value) -> Option<String> {
+    let secs = value.as_i64().or_else(|| value.as_f64().map(|f| f as i64))?;
+    let date = chrono::DateTime::from_timestamp(secs, 0)?;
+    let now = chrono::Utc::now();
+    let note = match claim {
+        "exp" if date < now => " (expired)",
+        "exp" => " (not yet expired)",
+        "nbf" if date > now => " (not yet valid)",
+        _ => "",
+    };
+    return Some(format!("{}: {}{}", claim, date.to_rfc3339(), note));
+}
+
+fn verify_hmac(jwt: &Jwt, alg: &str, secret: &str) -> Result<bool, String> {
+    let input = jwt.signing_input.as_bytes();
+    let key = secret.as_bytes();
+    return match alg {
+        "HS256" => Ok(Hmac::<Sha256>::new_from_slice(key).unwrap().chain_update(input).verify_slice(&jwt.signature).is_ok()),
+        "HS384" => Ok(Hmac::<Sha384>::new_from_slice(key).unwrap().chain_update(input).verify_slice(&jwt.signature).is_ok()),
+        "HS512" => Ok(Hmac::<Sha512>::new_from_slice(key).unwrap().chain_update(input).verify_slice(&jwt.signature).is_ok()),
+        _ => Err(format!("A secret cannot verify {} tokens.", alg)),
+    };
+}
+
+/// Accepts both SPKI (`BEGIN PUBLIC KEY`) and PKCS#1 (`BEGIN RSA PUBLIC KEY`) PEM.
+fn verify_rs256(jwt: &Jwt, pem: &str) -> Result<bool, String> {
+    let key = rsa::RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| rsa::RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|e| format!("Unable to read RSA public key: {}", e))?;
+    let verifying_key = rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key);
+    let signature = match rsa::pkcs1v15::Signature::try_from(jwt.signature.as_slice()) {
+        Ok(sig) => sig,
+        Err(_) => return Ok(false),
+    };
+    return Ok(verifying_key.verify(jwt.signing_input.as_bytes(), &signature).is_ok());
+}
+
+/// ES256 signatures are the raw 64-byte `r || s` concatenation, not DER.
+fn verify_es256(jwt: &Jwt, pem: &str) -> Result<bool, String> {
+    let key = p256::ecdsa::VerifyingKey::from_public_key_pem(pem)
+        .map_err(|e| format!("Unable to read P-256 public key: {}", e))?;
+    let signature = match p256::ecdsa::Signature::from_slice(&jwt.signature) {
+        Ok(sig) => sig,
+        Err(_) => return Ok(false),
+    };
+    return Ok(key.verify(jwt.signing_input.as_bytes(), &signature).is_ok());
+}
+
+fn verify_signature(jwt: &Jwt, key: &JwtKey) -> String {
+    let alg = jwt.header.get("alg").and_then(Value::as_str).unwrap_or("none");
+    let result = match (key, alg) {
+        (JwtKey::None, _) => return "not checked (pass --secret=<secret> or --key=<public.pem>)".to_string(),
+        (_, "none") => return "NONE (token is unsigned)".to_string(),
+        (JwtKey::Secret(secret), _) => verify_hmac(jwt, alg, secret),
+        (JwtKey::Pem(pem), "RS256") => verify_rs256(jwt, pem),
+        (JwtKey::Pem(pem), "ES256") => verify_es256(jwt, pem),
+        (JwtKey::Pem(_), _) => Err(format!("A PEM key cannot verify {} tokens.", alg)),
+    };
+    return match result {
+        Ok(true) => format!("valid ({})", alg),
+        Ok(false) => format!("INVALID ({})", alg),
+        Err(e) => format!("unable to verify: {}", e),
+    };
+}
+
+/// Prints the decoded header and payload, the registered time claims as dates,
+/// and the outcome of signature verification when a key is supplied.
+pub fn jwt_decode(text: &str, key: JwtKey) -> String {
+    let jwt = match parse_jwt(text) {
+        Ok(jwt) => jwt,
+        Err(e) => return format!("Invalid JWT: {}", e),
+    };
+    let mut sections = vec![
+        format!("Header:\n{}", serde_json::to_string_pretty(&jwt.header).unwrap()),
+        format!("Payload:\n{}", serde_json::to_string_pretty(&jwt.payload).unwrap()),
+    ];
+    let dates: Vec<String> = ["iat", "nbf", "exp"]
+        .iter()
+        .filter_map(|claim| jwt.payload.get(claim).and_then(|v| describe_timestamp(claim, v)))
+        .collect();
+    if !dates.is_empty() {
+        sections.push(format!("Dates:\n{}", dates.join("\n")));
+    }
+    sections.push(format!("Signature: {}", verify_signature(&jwt, &key)));
+    return sections.join("\n\n");
+}
@@ -0,0 +1,150 @@
+use crate::utils;
+
+/// Indentation and line width shared by the code formatters: `--indent=<n>` or
+/// `--tabs`, and `--width=<n>` (80 columns by default).
+pub struct Layout {
+    pub indent: String,
+    pub width: usize,
+}
+
+impl Layout {
+    pub fn from_args(args: &Vec<String>) -> Layout {
+        return Layout { indent: utils::get_indent(args), width: utils::get_usize_option(args, "width", 80) };
+    }
+
+    /// Columns taken by `text` printed at `depth`, counting a tab as four.
+    pub fn columns(&self, depth: usize, text: &str) -> usize {
+        let pad: usize = self.indent.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+        return pad * depth + text.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum::<usize>();
+    }
+
+    /// Rewrites output indented with `unit` spaces per level to use this indentation.
+    /// Lines that start inside a `/* */` comment or a string are content, not structure,
+    /// so they are left as they are.
+    pub fn reindent(&self, text: &str, unit: usize) -> String {
+        let mut comment = false;
+        let mut quote: Option<char> = None;
+        let lines: Vec<String> = text.lines().map(|line| {
+            let structural = !comment && quote.is_none();
+            let chars: Vec<char> = line.chars().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                match (comment, quote, chars[i]) {
+                    (true, _, '*') if chars.get(i + 1) == Some(&'/') => { comment = false; i += 1; },
+                    (true, _, _) => {},
+                    (false, Some(_), '\\') => i += 1,
+                    (false, Some(q), c) if c == q => quote = None,
+                    (false, Some(_), _) => {},
+                    (false, None, '/') if chars.get(i + 1) == Some(&'*') => { comment = true; i += 1; },
+                    (false, None, '"' | '\'') => quote = Some(chars[i]),
+                    _ => {},
+                }
+                i += 1;
+            }
+            if !structural {
+                return line.to_string();
+            }
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            format!("{}{}{}", self.indent.repeat(spaces / unit), " ".repeat(spaces % unit), &line[spaces..])
+        }).collect();
+        let mut result = lines.join("\n");
+        if text.ends_with('\n') {
+            result.push('\n');
+        }
+        return result;
+    }
+}
+
+/// Builds output line by line, indenting each finished line to the current depth.
+pub struct Printer<'a> {
+    pub layout: &'a Layout,
+    pub lines: Vec<String>,
+    pub line: String,
+    pub depth: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(layout: &'a Layout) -> Printer<'a> {
+        return Printer { layout, lines: Vec::new(), line: String::new(), depth: 0 };
+    }
+
+    /// Appends to the current line, separated by a space when `space` is set.
+    pub fn push(&mut self, text: &str, space: bool) {
+        if space && !self.line.is_empty() {
+            self.line.push(' ');
+        }
+        self.line.push_str(text);
+    }
+
+    pub fn flush(&mut self) {
+        let line = self.line.trim_end().to_string();
+        if !line.is_empty() {
+            self.lines.push(format!("{}{}", self.layout.indent.repeat(self.depth), line));
+        }
+        self.line.clear();
+    }
+
+    pub fn push_line(&mut self, text: &str) {
+        self.flush();
+        self.push(text, false);
+        self.flush();
+    }
+
+    /// Ends the current line and leaves one blank line, never at the start or twice.
+    pub fn blank(&mut self) {
+        self.flush();
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    /// Whether `text` still fits on the current line.
+    pub fn fits(&self, text: &str) -> bool {
+        return self.layout.columns(self.depth, &format!("{}{}", self.line, text)) <= self.layout.width;
+    }
+
+    pub fn finish(mut self) -> String {
+        self.flush();
+        while self.lines.last().is_some_and(|line| line.is_empty()) {
+            self.lines.pop();
+        }
+        return self.lines.join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(indent: &str, width: usize) -> Layout {
+        return Layout { indent: indent.to_string(), width };
+    }
+
+    #[test]
+    fn reindent_changes_structural_indentation_only() {
+        let css = "/*! License\n    indented  text\n*/\na {\n  color: red;\n  & b {\n    content: \"x /* y\";\n  }\n}\n";
+        let expected = "/*! License\n    indented  text\n*/\na {\n\tcolor: red;\n\t& b {\n\t\tcontent: \"x /* y\";\n\t}\n}\n";
+        assert_eq!(layout("\t", 80).reindent(css, 2), expected);
+        assert_eq!(layout("  ", 80).reindent(css, 2), css);
+    }
+
+    #[test]
+    fn printer_indents_and_squeezes_blank_lines() {
+        let layout = layout("    ", 20);
+        let mut out = Printer::new(&layout);
+        out.blank();
+        out.push("a", true);
+        out.push("{", true);
+        out.flush();
+        out.depth += 1;
+        assert!(out.fits("exactly fifteen"));
+        assert!(!out.fits("more than sixteen"));
+        out.push_line("b;");
+        out.blank();
+        out.blank();
+        out.depth -= 1;
+        out.push_line("}");
+        out.blank();
+        assert_eq!(out.finish(), "a {\n    b;\n\n}");
+    }
+}
@@ -0,0 +1,225 @@
+use std::io::Read;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine as _;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use percent_encoding::percent_decode;
+use crate::{escapes, jwt};
+
+const MAX_DEPTH: usize = 6;
+const MAX_NODES: usize = 500;
+const PRINTABLE_THRESHOLD: f64 = 0.95;
+const PREVIEW_LEN: usize = 72;
+/// Decompressed output is capped, since a tiny gzip or zlib stream can expand to
+/// gigabytes. Anything larger is not treated as a match.
+const MAX_DECOMPRESSED: u64 = 8 << 20;
+
+struct Decoder {
+    name: &'static str,
+    decode: fn(&[u8]) -> Option<Vec<u8>>,
+}
+
+const DECODERS: &[Decoder] = &[
+    Decoder { name: "jwt", decode: decode_jwt },
+    Decoder { name: "gunzip", decode: decode_gzip },
+    Decoder { name: "zlib", decode: decode_zlib },
+    Decoder { name: "hex", decode: decode_hex },
+    Decoder { name: "binary", decode: decode_binary },
+    Decoder { name: "base64", decode: decode_base64 },
+    Decoder { name: "base64url", decode: decode_base64url },
+    Decoder { name: "url_decode", decode: decode_url },
+    Decoder { name: "html_decode", decode: decode_html },
+    Decoder { name: "unescape_unicode", decode: decode_unicode_escapes },
+    Decoder { name: "unescape_hex", decode: decode_hex_escapes },
+];
+
+struct Candidate {
+    chain: Vec<&'static str>,
+    output: Vec<u8>,
+    score: f64,
+}
+
+fn lenient_engine(alphabet: &alphabet::Alphabet) -> GeneralPurpose {
+    let config = GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    return GeneralPurpose::new(alphabet, config);
+}
+
+fn as_text(data: &[u8]) -> Option<&str> {
+    return std::str::from_utf8(data).ok();
+}
+
+/// Whitespace and line breaks are common in pasted blobs, so they are ignored.
+fn compact(data: &[u8]) -> Option<String> {
+    let text = as_text(data)?;
+    let result: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if result.is_empty() { return None; }
+    return Some(result);
+}
+
+fn decode_base64_with(data: &[u8], alphabet: &alphabet::Alphabet, extra: [char; 2]) -> Option<Vec<u8>> {
+    let text = compact(data)?;
+    let body = text.trim_end_matches('=');
+    if body.len() < 4 || !body.chars().all(|c| c.is_ascii_alphanumeric() || extra.contains(&c)) {
+        return None;
+    }
+    return lenient_engine(alphabet).decode(&text).ok();
+}
+
+fn decode_base64(data: &[u8]) -> Option<Vec<u8>> {
+    return decode_base64_with(data, &alphabet::STANDARD, ['+', '/']);
+}
+
+fn decode_base64url(data: &[u8]) -> Option<Vec<u8>> {
+    let text = compact(data)?;
+    if !text.contains(['-', '_']) { return None; }
+    return decode_base64_with(data, &alphabet::URL_SAFE, ['-', '_']);
+}
+
+/// Accepts `0x`, `\x`, `:` and whitespace separators between the digits.
+fn decode_hex(data: &[u8]) -> Option<Vec<u8>> {
+    let text = compact(data)?;
+    let clean = text.replace("0x", "").replace("\\x", "").replace(':', "");
+    if clean.len() < 2 { return None; }
+    return hex::decode(clean).ok();
+}
+
+fn decode_binary(data: &[u8]) -> Option<Vec<u8>> {
+    let text = compact(data)?;
+    if text.len() < 8 || !text.len().is_multiple_of(8) || !text.chars().all(|c| c == '0' || c == '1') {
+        return None;
+    }
+    return text.as_bytes().chunks(8).map(|bits| u8::from_str_radix(std::str::from_utf8(bits).ok()?, 2).ok()).collect();
+}
+
+fn decode_url(data: &[u8]) -> Option<Vec<u8>> {
+    let text = as_text(data)?;
+    let has_escape = text.as_bytes().windows(3).any(|w| w[0] == b'%' && w[1].is_ascii_hexdigit() && w[2].is_ascii_hexdigit());
+    if !has_escape { return None; }
+    return Some(percent_decode(data).collect());
+}
+
+fn decode_html(data: &[u8]) -> Option<Vec<u8>> {
+    let text = as_text(data)?;
+    if !text.contains('&') || !text.contains(';') { return None; }
+    return Some(htmlize::unescape(text).into_owned().into_bytes());
+}
+
+fn decode_unicode_escapes(data: &[u8]) -> Option<Vec<u8>> {
+    let text = as_text(data)?;
+    if !text.contains("\\u") { return None; }
+    return Some(escapes::unescape_unicode(text).into_bytes());
+}
+
+fn decode_hex_escapes(data: &[u8]) -> Option<Vec<u8>> {
+    let text = as_text(data)?;
+    if !text.contains("\\x") { return None; }
+    return Some(escapes::unescape_hex(text).into_bytes());
+}
+
+fn read_limited<R: Read>(decoder: R) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    decoder.take(MAX_DECOMPRESSED + 1).read_to_end(&mut result).ok()?;
+    if result.len() as u64 > MAX_DECOMPRESSED { return None; }
+    return Some(result);
+}
+
+fn decode_gzip(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0x1f, 0x8b]) { return None; }
+    return read_limited(GzDecoder::new(data));
+}
+
+/// A zlib stream starts with a CMF/FLG pair whose 16-bit value is a multiple of 31.
+fn decode_zlib(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 2 || data[0] & 0x0f != 8 || !(((data[0] as u16) << 8) | data[1] as u16).is_multiple_of(31) {
+        return None;
+    }
+    return read_limited(ZlibDecoder::new(data));
+}
+
+fn decode_jwt(data: &[u8]) -> Option<Vec<u8>> {
+    let text = as_text(data)?.trim();
+    if !text.starts_with("eyJ") || text.split('.').count() != 3 { return None; }
+    let result = jwt::jwt_decode(text, jwt::JwtKey::None);
+    if result.starts_with("Invalid JWT") { return None; }
+    return Some(result.into_bytes());
+}
+
+/// Share of characters that are printable, counting invalid UTF-8 as unprintable.
+fn printable_ratio(data: &[u8]) -> f64 {
+    let text = String::from_utf8_lossy(data);
+    let total = text.chars().count();
+    if total == 0 { return 0.0; }
+    let printable = text.chars()
+        .filter(|c| *c != char::REPLACEMENT_CHARACTER && (!c.is_control() || matches!(c, '\n' | '\r' | '\t')))
+        .count();
+    return printable as f64 / total as f64;
+}
+
+/// Breadth-first search over every decoder that accepts the current output.
+fn explore(input: &[u8]) -> Vec<Candidate> {
+    let mut found: Vec<Candidate> = Vec::new();
+    let mut frontier: Vec<(Vec<&'static str>, Vec<u8>)> = vec![(Vec::new(), input.to_vec())];
+    let mut seen: Vec<Vec<u8>> = vec![input.to_vec()];
+    for _ in 0..MAX_DEPTH {
+        let mut next = Vec::new();
+        for (chain, data) in frontier {
+            for decoder in DECODERS {
+                if seen.len() >= MAX_NODES { break; }
+                let output = match (decoder.decode)(&data) {
+                    Some(output) if !output.is_empty() && !seen.contains(&output) => output,
+                    _ => continue,
+                };
+                seen.push(output.clone());
+                let mut new_chain = chain.clone();
+                new_chain.push(decoder.name);
+                found.push(Candidate { chain: new_chain.clone(), output: output.clone(), score: printable_ratio(&output) });
+                next.push((new_chain, output));
+            }
+        }
+        frontier = next;
+    }
+    return found;
+}
+
+fn preview(data: &[u8]) -> String {
+    let text: String = String::from_utf8_lossy(data).chars()
+        .map(|c| if c.is_control() { '.' } else { c })
+        .collect();
+    if text.chars().count() <= PREVIEW_LEN { return text; }
+    return format!("{}...", text.chars().take(PREVIEW_LEN).collect::<String>());
+}
+
+/// Readable results from longer chains rank first, since each decoder only accepts
+/// strictly formatted input; unreadable results are ordered by how printable they are.
+pub fn magic(text: &str) -> String {
+    let mut candidates = explore(text.trim().as_bytes());
+    if candidates.is_empty() {
+        return "No known encoding detected.".to_string();
+    }
+    candidates.sort_by(|a, b| {
+        let a_readable = a.score >= PRINTABLE_THRESHOLD;
+        let b_readable = b.score >= PRINTABLE_THRESHOLD;
+        b_readable.cmp(&a_readable)
+            .then_with(|| if a_readable { b.chain.len().cmp(&a.chain.len()) } else { std::cmp::Ordering::Equal })
+            .then_with(|| b.score.total_cmp(&a.score))
+    });
+    let best = &candidates[0];
+    let mut result = format!(
+        "Most likely: {} (printable {:.0}%)\n\n{}",
+        best.chain.join(" -> "),
+        best.score * 100.0,
+        String::from_utf8_lossy(&best.output),
+    );
+    if candidates.len() > 1 {
+        result.push_str("\n\nOther candidates:");
+        for candidate in candidates.iter().skip(1).take(5) {
+            result.push_str(&format!(
+                "\n  {} (printable {:.0}%): {}",
+                candidate.chain.join(" -> "),
+                candidate.score * 100.0,
+                preview(&candidate.output),
+            ));
+        }
+    }
+    return result;
+}
@@ -1,14 +1,34 @@
 use std::{env, fs};
+use std::io::Write;
 
 mod utils;
 mod encoding;
+mod escapes;
+mod crypto;
+mod dockerfile;
+mod graphql;
+mod html;
+mod jq;
+mod js;
+mod json;
+mod json_tools;
+mod jwt;
+mod layout;
+mod magic;
+mod protobuf;
+mod sql;
 mod casing;
+mod ciphers;
+mod compression;
 mod conversion;
 mod format;
+mod generators;
 mod text_utils;
+mod url_utils;
+mod validation;
 
 enum PathChange {
-    JsonCsv, CsvJson, JsonYaml, YamlJson, NoChange,
+    JsonCsv, CsvJson, JsonYaml, YamlJson, Json5Json, AddExtension(String), InsertExtension(String), StripExtension(String), Print, NoChange,
 }
 
 fn main() {
@@ -18,7 +38,6 @@ fn main() {
         return;
     }
     let flags = utils::get_flags(&args);
-    let text: String;
     if utils::flags_contains(&flags, 'i') {
         // TODO
         // start_interactive_session()
@@ -28,43 +47,91 @@ fn main() {
         utils::print_commands();
         return;
     }
-    if utils::flags_contains(&flags, 's') {
+    let command_family = utils::get_command_family(&args);
+    let text = if let utils::CommandFamily::Generator | utils::CommandFamily::Compression = command_family { String::new() }
+    else if utils::flags_contains(&flags, 's') {
         let idx = utils::get_flag_idx(&flags, 's');
-        text = args[idx].clone();
+        args[idx].clone()
     }
-    else { text = utils::get_file_contents(&args); }
-    let command_family = utils::get_command_family(&args);
+    else { utils::get_file_contents(&args) };
     let change: PathChange;
-    let result: String;
+    let result: Vec<u8>;
     match command_family {
         utils::CommandFamily::Casing => {
             let case_op = casing::select_case_option(&args);
-            result = casing::handle_case_operation(&text, case_op);
+            result = casing::handle_case_operation(&text, case_op, &args).into_bytes();
             change = PathChange::NoChange;
         },
         utils::CommandFamily::Encoding => {
             let encoding_op = encoding::select_encoding_option(&args);
-            result = encoding::handle_encoding_operation(&text, encoding_op);
-            change = PathChange::NoChange;
+            change = match encoding_op {
+                encoding::EncodingOp::JwtDecode | encoding::EncodingOp::VerifyPassword | encoding::EncodingOp::Magic => PathChange::Print,
+                _ => PathChange::NoChange,
+            };
+            result = encoding::handle_encoding_operation(&text, encoding_op, &args).into_bytes();
         },
         utils::CommandFamily::Format => {
             let format_op = format::select_format_option(&args);
-            result = format::handle_format_operation(&text, format_op);
-            change = PathChange::NoChange;
+            change = match format::inserted_extension(&format_op, &args) {
+                Some(ext) => PathChange::InsertExtension(ext),
+                None => PathChange::NoChange,
+            };
+            result = format::handle_format_operation(&text, format_op, &args).into_bytes();
         },
         utils::CommandFamily::TextUtils => {
             let format_op = text_utils::select_text_util_option(&args);
-            result = text_utils::handle_text_util_operation(&text, format_op);
+            result = text_utils::handle_text_util_operation(&text, format_op, &args).into_bytes();
+            change = PathChange::NoChange;
+        },
+        utils::CommandFamily::UrlUtils => {
+            let url_op = url_utils::select_url_option(&args);
+            change = match url_op {
+                url_utils::UrlOp::ParseUrl | url_utils::UrlOp::CheckHomograph => PathChange::Print,
+                _ => PathChange::NoChange,
+            };
+            result = url_utils::handle_url_operation(&text, url_op, &args).into_bytes();
+        },
+        utils::CommandFamily::Generator => {
+            let generator_op = generators::select_generator_option(&args);
+            result = generators::handle_generator_operation(generator_op, &args).into_bytes();
+            change = PathChange::NoChange;
+        },
+        utils::CommandFamily::Escape => {
+            let escape_op = escapes::select_escape_option(&args);
+            result = escapes::handle_escape_operation(&text, escape_op, &args).into_bytes();
             change = PathChange::NoChange;
         },
+        utils::CommandFamily::JsonTools => {
+            let json_tool_op = json_tools::select_json_tool_option(&args);
+            result = json_tools::handle_json_tool_operation(&text, json_tool_op, &args).into_bytes();
+            change = PathChange::Print;
+        },
+        utils::CommandFamily::Validation => {
+            let validation_op = validation::select_validation_option(&args);
+            result = match validation::handle_validation_operation(&text, validation_op, &args) {
+                Ok(message) => message.into_bytes(),
+                Err(report) => {
+                    eprintln!("{}", report);
+                    std::process::exit(1);
+                },
+            };
+            change = PathChange::Print;
+        },
+        utils::CommandFamily::Compression => {
+            let compression_op = compression::select_compression_option(&args);
+            result = compression::handle_compression_operation(&utils::get_input_bytes(&args), &compression_op, &args);
+            let ext = compression::file_extension(&compression_op, &args);
+            change = if compression::is_compress(&compression_op) { PathChange::AddExtension(ext) } else { PathChange::StripExtension(ext) };
+        },
         utils::CommandFamily::Conversion => {
             let conversion_op = conversion::select_conversion_option(&args);
-            result = conversion::handle_conversion_operation(&text, &conversion_op);
+            result = conversion::handle_conversion_operation(&text, &conversion_op).into_bytes();
             match conversion_op {
                 conversion::ConversionOp::YamlToJson => change = PathChange::YamlJson,
                 conversion::ConversionOp::JsonToYaml => change = PathChange::JsonYaml,
                 conversion::ConversionOp::CsvToJson=> change = PathChange::CsvJson,
                 conversion::ConversionOp::JsonToCsv => change = PathChange::JsonCsv,
+                conversion::ConversionOp::Json5ToJson => change = PathChange::Json5Json,
                 _ => change = PathChange::NoChange,
             }
         },
@@ -73,7 +140,8 @@ fn main() {
             return;
         },
     }
-    let path = utils::find_file_path(&args);
+    // Reports are printed; only transformed text is written back to the input file.
+    let path = if let PathChange::Print = change { None } else { utils::find_file_path(&args) };
     handle_result(&result, path, change);
 }
 
@@ -85,13 +153,32 @@ fn update_path(path: &str, change: PathChange) -> String {
         PathChange::CsvJson => format!("{}.json", prefix),
         PathChange::JsonYaml => format!("{}.yaml", prefix),
         PathChange::YamlJson => format!("{}.json", prefix),
+        PathChange::Json5Json => format!("{}.json", prefix),
+        PathChange::AddExtension(ext) => format!("{}.{}", path, ext),
+        PathChange::InsertExtension(ext) => match path.rfind('.') {
+            Some(dot) if !path[dot..].contains('/') => format!("{}.{}{}", &path[..dot], ext, &path[dot..]),
+            _ => format!("{}.{}", path, ext),
+        },
+        PathChange::StripExtension(ext) => match path.strip_suffix(&format!(".{}", ext)) {
+            Some(stripped) => stripped.to_string(),
+            None => format!("{}.out", path),
+        },
         _ => path.to_string(),
     }
 }
 
-fn handle_result(result: &String, path: Option<String>, change: PathChange) {
+/// Results are bytes so binary output (e.g. compressed data) survives untouched;
+/// text is still printed with a trailing newline.
+fn handle_result(result: &[u8], path: Option<String>, change: PathChange) {
     match path {
-        None => println!("{}", result),
+        None => match std::str::from_utf8(result) {
+            Ok(text) => println!("{}", text),
+            Err(_) => {
+                if let Err(e) = std::io::stdout().write_all(result) {
+                    eprintln!("Unable to write output: {}", e);
+                }
+            },
+        },
         _ => { 
             match change {
                 PathChange::NoChange => {
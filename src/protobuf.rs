@@ -0,0 +1,295 @@
+use crate::layout::{Layout, Printer};
+
+#[derive(PartialEq)]
+enum Kind {
+    Word, Punct, Str, Comment,
+}
+
+struct Token {
+    kind: Kind,
+    text: String,
+    /// Line breaks between this token and the previous one.
+    newlines: usize,
+}
+
+fn is_word_char(c: char) -> bool {
+    return c.is_ascii_alphanumeric() || c == '_' || c == '.';
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut newlines = 0;
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start = i;
+        let kind = if c == '\n' {
+            newlines += 1;
+            line += 1;
+            i += 1;
+            continue;
+        }
+        else if c.is_whitespace() || c == '\u{feff}' {
+            i += 1;
+            continue;
+        }
+        else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' { i += 1; }
+            Kind::Comment
+        }
+        else if c == '/' && next == Some('*') {
+            i += 2;
+            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') { i += 1; }
+            if i + 1 >= chars.len() {
+                return Err(format!("Unterminated comment starting on line {}", line));
+            }
+            i += 2;
+            Kind::Comment
+        }
+        else if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            if chars.get(i) != Some(&c) {
+                return Err(format!("Unterminated string on line {}", line));
+            }
+            i += 1;
+            Kind::Str
+        }
+        else if is_word_char(c) || (c == '-' || c == '+') && next.is_some_and(|n| n.is_ascii_alphanumeric() || n == '.') {
+            i += 1;
+            while i < chars.len() && (is_word_char(chars[i]) || matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')) {
+                i += 1;
+            }
+            Kind::Word
+        }
+        else if "{}()[]<>;,=:".contains(c) {
+            i += 1;
+            Kind::Punct
+        }
+        else {
+            return Err(format!("Unexpected character '{}' on line {}", c, line));
+        };
+        let text: String = chars[start..i].iter().collect();
+        line += text.matches('\n').count();
+        tokens.push(Token { kind, text, newlines });
+        newlines = 0;
+    }
+    return Ok(tokens);
+}
+
+fn space_before(prev: Option<&Token>, token: &Token) -> bool {
+    let prev = match prev {
+        Some(prev) => prev,
+        None => return false,
+    };
+    return match (prev.text.as_str(), token.text.as_str()) {
+        (_, ";" | "," | ")" | "]" | ">" | ":") => false,
+        (")", next) if next.starts_with('.') => false,
+        ("(" | "[" | "<", _) => false,
+        ("returns" | "option" | "," | "=", "(") => true,
+        (_, "(" | "<") => false,
+        _ => true,
+    };
+}
+
+/// Index of the bracket closing the one opened at `open`.
+fn matching(tokens: &[Token], open: usize) -> Result<usize, String> {
+    let mut depth = 0;
+    for (idx, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != Kind::Punct {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(idx);
+                }
+            },
+            _ => {},
+        }
+    }
+    return Err(format!("Unclosed '{}'", tokens[open].text));
+}
+
+fn render_inline(tokens: &[Token]) -> String {
+    let mut result = String::new();
+    let mut prev: Option<&Token> = None;
+    for token in tokens.iter().filter(|token| token.kind != Kind::Comment) {
+        if space_before(prev, token) {
+            result.push(' ');
+        }
+        result.push_str(&token.text);
+        prev = Some(token);
+    }
+    return result;
+}
+
+/// Field options: `[a = 1, b = 2]` on the current line when it fits, otherwise one
+/// option per line.
+fn print_options(out: &mut Printer, tokens: &[Token], space: bool) {
+    let mut items: Vec<&[Token]> = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.text.as_str() {
+            "(" | "[" | "{" | "<" if token.kind == Kind::Punct => depth += 1,
+            ")" | "]" | "}" | ">" if token.kind == Kind::Punct => depth -= 1,
+            "," if depth == 0 && token.kind == Kind::Punct => {
+                items.push(&tokens[start..idx]);
+                start = idx + 1;
+            },
+            _ => {},
+        }
+    }
+    items.push(&tokens[start..]);
+    let rendered: Vec<String> = items.iter().map(|item| render_inline(item)).filter(|item| !item.is_empty()).collect();
+    let inline = format!("[{}]", rendered.join(", "));
+    let has_comment = tokens.iter().any(|token| token.kind == Kind::Comment);
+    if !has_comment && out.fits(&format!(" {};", inline)) {
+        out.push(&inline, space);
+        return;
+    }
+    out.push("[", space);
+    out.flush();
+    out.depth += 1;
+    let count = rendered.len();
+    for (idx, item) in rendered.iter().enumerate() {
+        out.push_line(&format!("{}{}", item, if idx + 1 < count { "," } else { "" }));
+    }
+    out.depth -= 1;
+    out.push("]", false);
+}
+
+/// One statement per line, blocks indented, single blank lines kept, spacing around
+/// `=`, `,` and `:` normalised, and field options broken over several lines when they
+/// would overflow the width. Braces after `=` or `:` hold text-format option values,
+/// printed one field per line.
+pub fn format(source: &str, layout: &Layout) -> Result<String, String> {
+    let tokens = tokenize(source)?;
+    let mut out = Printer::new(layout);
+    // Whether each open brace holds a text-format value rather than declarations.
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut prev: Option<&Token> = None;
+    let mut statement_done = false;
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        if token.kind == Kind::Comment {
+            if token.newlines == 0 && !out.line.is_empty() {
+                out.push(&token.text, true);
+                out.flush();
+            }
+            else {
+                if token.newlines > 1 {
+                    out.blank();
+                }
+                out.push_line(&token.text);
+            }
+            statement_done = false;
+            idx += 1;
+            continue;
+        }
+        let in_text_value = blocks.last().is_some_and(|text| *text);
+        let starts_field = in_text_value && matches!(token.kind, Kind::Word)
+            && tokens.get(idx + 1).is_some_and(|next| next.text == ":" || next.text == "{" || next.text == "<")
+            && prev.is_some_and(|prev| prev.text != ":");
+        if (statement_done && token.text != ";") || starts_field {
+            out.flush();
+            if token.newlines > 1 {
+                out.blank();
+            }
+        }
+        statement_done = false;
+        match (&token.kind, token.text.as_str()) {
+            (Kind::Punct, "{") => {
+                blocks.push(in_text_value || prev.is_some_and(|prev| prev.text == "=" || prev.text == ":"));
+                out.push("{", true);
+                out.flush();
+                out.depth += 1;
+            },
+            (Kind::Punct, "}") => {
+                out.flush();
+                if blocks.pop().is_none() {
+                    return Err("Unmatched '}'".to_string());
+                }
+                out.depth -= 1;
+                out.push("}", false);
+                statement_done = true;
+            },
+            (Kind::Punct, ";") => {
+                out.push(";", false);
+                statement_done = true;
+            },
+            (Kind::Punct, "[") if !out.line.is_empty() && !in_text_value => {
+                let end = matching(&tokens, idx)?;
+                print_options(&mut out, &tokens[idx + 1..end], space_before(prev, token));
+                idx = end;
+            },
+            (_, text) => out.push(text, space_before(prev, token)),
+        }
+        prev = Some(&tokens[idx]);
+        idx += 1;
+    }
+    if !blocks.is_empty() {
+        return Err("Unclosed '{'".to_string());
+    }
+    return Ok(out.finish());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_with_width(source: &str, width: usize) -> String {
+        return format(source, &Layout { indent: "  ".to_string(), width }).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn assert_idempotent(source: &str) {
+        let once = format_with_width(source, 80);
+        assert_eq!(format_with_width(&once, 80), once);
+    }
+
+    #[test]
+    fn statements_maps_and_comments() {
+        let source = "syntax=\"proto3\";\nmessage User{\n  string name=1;  // the name\n  map<string,int32> scores=2;\n\n\n  oneof contact{string email=3;string phone=4;}\n}";
+        let expected = "syntax = \"proto3\";\nmessage User {\n  string name = 1; // the name\n  map<string, int32> scores = 2;\n\n  oneof contact {\n    string email = 3;\n    string phone = 4;\n  }\n}";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn field_options_wrap_at_width() {
+        let source = "message A { repeated string tags = 3 [packed = true, deprecated = true, (my.option) = \"long value\"]; }";
+        assert_eq!(
+            format_with_width(source, 100),
+            "message A {\n  repeated string tags = 3 [packed = true, deprecated = true, (my.option) = \"long value\"];\n}"
+        );
+        let expected = "message A {\n  repeated string tags = 3 [\n    packed = true,\n    deprecated = true,\n    (my.option) = \"long value\"\n  ];\n}";
+        assert_eq!(format_with_width(source, 40), expected);
+        assert_eq!(format_with_width(expected, 40), expected);
+    }
+
+    #[test]
+    fn text_format_option_values() {
+        let source = "service S { rpc Get(Req)returns(Res){ option (google.api.http)={get:\"/v1/{id}\" additional_bindings{post:\"/v1\"}}; } }";
+        let expected = "service S {\n  rpc Get(Req) returns (Res) {\n    option (google.api.http) = {\n      get: \"/v1/{id}\"\n      additional_bindings {\n        post: \"/v1\"\n      }\n    };\n  }\n}";
+        assert_eq!(format_with_width(source, 80), expected);
+        assert_idempotent(source);
+    }
+
+    #[test]
+    fn errors() {
+        let layout = Layout { indent: "  ".to_string(), width: 80 };
+        assert!(format("message A { string b = 1;", &layout).is_err());
+        assert!(format("message A { string b = \"open; }", &layout).is_err());
+        assert!(format("/* never closed", &layout).is_err());
+        assert!(format("message A { string b = 1; } }", &layout).is_err());
+    }
+}
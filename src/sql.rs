@@ -0,0 +1,165 @@
+use sqlformat::Dialect;
+
+pub enum SqlDialect {
+    Generic, Postgres, MySql, Sqlite, TSql,
+}
+
+pub fn str_to_dialect(name: &str) -> SqlDialect {
+    return match name.to_lowercase().as_str() {
+        "generic" | "ansi" => SqlDialect::Generic,
+        "postgres" | "postgresql" | "pg" => SqlDialect::Postgres,
+        "mysql" | "mariadb" => SqlDialect::MySql,
+        "sqlite" => SqlDialect::Sqlite,
+        "tsql" | "t-sql" | "mssql" | "sqlserver" => SqlDialect::TSql,
+        _ => panic!("Unknown SQL dialect '{}'. Use postgres, mysql, sqlite, tsql or generic.", name),
+    };
+}
+
+/// sqlformat only distinguishes Postgres and SQL Server syntax; MySQL and SQLite
+/// format correctly with the generic rules.
+pub fn formatter_dialect(dialect: &SqlDialect) -> Dialect {
+    return match dialect {
+        SqlDialect::Postgres => Dialect::PostgreSql,
+        SqlDialect::TSql => Dialect::SQLServer,
+        _ => Dialect::Generic,
+    };
+}
+
+enum Token {
+    /// Anything copied verbatim: words, operators, literals and quoted identifiers.
+    Text(String),
+    /// Whitespace or a comment, which the minifier may drop.
+    Gap,
+}
+
+/// Length in chars of a quoted run starting at `start`, honouring doubled closing
+/// quotes and, where the dialect allows it, backslash escapes.
+fn quoted_len(chars: &[char], start: usize, close: char, backslash: bool) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if backslash && chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i] == close {
+            if chars.get(i + 1) == Some(&close) {
+                i += 2;
+                continue;
+            }
+            return i + 1 - start;
+        }
+        i += 1;
+    }
+    return chars.len() - start;
+}
+
+/// Postgres dollar quoting: `$$ ... $$` or `$tag$ ... $tag$`.
+fn dollar_quoted_len(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+        i += 1;
+    }
+    if chars.get(i) != Some(&'$') || chars.get(start + 1).is_some_and(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let tag: String = chars[start..=i].iter().collect();
+    let body: String = chars[i + 1..].iter().collect();
+    return match body.find(&tag) {
+        Some(end) => Some(i + 1 - start + body[..end].chars().count() + tag.chars().count()),
+        None => Some(chars.len() - start),
+    };
+}
+
+fn tokenize(sql: &str, dialect: &SqlDialect) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let len = if c.is_whitespace() {
+            let mut j = i;
+            while j < chars.len() && chars[j].is_whitespace() { j += 1; }
+            tokens.push(Token::Gap);
+            i = j;
+            continue;
+        }
+        else if c == '-' && next == Some('-') || c == '#' && matches!(dialect, SqlDialect::MySql) {
+            while i < chars.len() && chars[i] != '\n' { i += 1; }
+            tokens.push(Token::Gap);
+            continue;
+        }
+        // MySQL `/*! ... */` comments hold version-gated SQL, so they are kept.
+        else if c == '/' && next == Some('*') && !(matches!(dialect, SqlDialect::MySql) && chars.get(i + 2) == Some(&'!')) {
+            let mut j = i + 2;
+            while j + 1 < chars.len() && !(chars[j] == '*' && chars[j + 1] == '/') { j += 1; }
+            i = (j + 2).min(chars.len());
+            tokens.push(Token::Gap);
+            continue;
+        }
+        else if c == '/' && next == Some('*') {
+            let mut j = i + 2;
+            while j + 1 < chars.len() && !(chars[j] == '*' && chars[j + 1] == '/') { j += 1; }
+            (j + 2).min(chars.len()) - i
+        }
+        else if c == '\'' {
+            let escaped = matches!(dialect, SqlDialect::MySql)
+                || matches!(dialect, SqlDialect::Postgres) && i > 0 && matches!(chars[i - 1], 'e' | 'E')
+                    && (i < 2 || !is_word_char(chars[i - 2]));
+            quoted_len(&chars, i, '\'', escaped)
+        }
+        else if c == '"' {
+            quoted_len(&chars, i, '"', matches!(dialect, SqlDialect::MySql))
+        }
+        else if c == '`' && matches!(dialect, SqlDialect::MySql | SqlDialect::Sqlite | SqlDialect::Generic) {
+            quoted_len(&chars, i, '`', false)
+        }
+        else if c == '[' && matches!(dialect, SqlDialect::TSql | SqlDialect::Sqlite) {
+            quoted_len(&chars, i, ']', false)
+        }
+        else if c == '$' && matches!(dialect, SqlDialect::Postgres | SqlDialect::Generic) {
+            dollar_quoted_len(&chars, i).unwrap_or(1)
+        }
+        else { 1 };
+        let text: String = chars[i..i + len].iter().collect();
+        match tokens.last_mut() {
+            Some(Token::Text(last)) if len == 1 && !matches!(c, '\'' | '"' | '`' | '[' | '$') => last.push(c),
+            _ => tokens.push(Token::Text(text)),
+        }
+        i += len;
+    }
+    return tokens;
+}
+
+fn is_word_char(c: char) -> bool {
+    return c.is_alphanumeric() || matches!(c, '_' | '$' | '@' | '#' | '\'' | '"' | '`' | '[' | ']' | '.');
+}
+
+fn is_operator_char(c: char) -> bool {
+    return matches!(c, '-' | '+' | '*' | '/' | '<' | '>' | '=' | '!' | '|' | '&' | ':' | '%' | '^' | '~');
+}
+
+/// Drops comments and collapses whitespace without touching string literals, quoted
+/// identifiers or dollar-quoted bodies. A single space is kept only where removing it
+/// would join two words or two operators.
+pub fn minify(sql: &str, dialect: &SqlDialect) -> String {
+    let mut result = String::new();
+    let mut gap = false;
+    for token in tokenize(sql, dialect) {
+        match token {
+            Token::Gap => gap = true,
+            Token::Text(text) => {
+                if gap && let (Some(last), Some(first)) = (result.chars().last(), text.chars().next()) {
+                    let joins_words = is_word_char(last) && is_word_char(first);
+                    let joins_operators = is_operator_char(last) && is_operator_char(first);
+                    if joins_words || joins_operators {
+                        result.push(' ');
+                    }
+                }
+                result.push_str(&text);
+                gap = false;
+            },
+        }
+    }
+    return result;
+}
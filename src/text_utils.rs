@@ -1,6 +1,7 @@
-use rand::{self, seq::SliceRandom};
+use rand::{self, rngs::StdRng, seq::SliceRandom, Rng};
 use regex::Regex;
 use itertools::Itertools;
+use crate::utils;
 
 pub enum TextUtilOp {
     Defang, Refang, Deburr, ShuffleLines, SumAll, CountChars, CollapseLines, DedupLines,
@@ -39,12 +40,13 @@ pub fn select_text_util_option(args: &Vec<String>) -> TextUtilOp {
     return TextUtilOp::Unknown
 }
 
-pub fn handle_text_util_operation(text: &str, op: TextUtilOp) -> String {
+pub fn handle_text_util_operation(text: &str, op: TextUtilOp, args: &Vec<String>) -> String {
+    let mut rng = utils::get_rng(args);
     return match op {
         TextUtilOp::Defang => defang(text),
         TextUtilOp::Refang => refang(text),
         TextUtilOp::Deburr => deburr(text),
-        TextUtilOp::ShuffleLines => shuffle_lines(text),
+        TextUtilOp::ShuffleLines => shuffle_lines(text, &mut rng),
         TextUtilOp::SumAll => sum_all(text),
         TextUtilOp::CountChars => count_chars(text),
         TextUtilOp::CollapseLines => collapse_lines(text),
@@ -53,7 +55,7 @@ pub fn handle_text_util_operation(text: &str, op: TextUtilOp) -> String {
         TextUtilOp::Trim => trim_input(text),
         TextUtilOp::NaturalSortLines => natural_sort_lines(text),
         TextUtilOp::ReverseString => reverse_string(text),
-        TextUtilOp::LoremIpsum => lorem_ipsum(),
+        TextUtilOp::LoremIpsum => lorem_ipsum(&mut rng),
         TextUtilOp::MdQuote => md_quote(text),
         TextUtilOp::ReplaceSmartQuotes => replace_smart_quotes(text),
         TextUtilOp::Unknown => panic!("Unknown text util operation specified."),
@@ -80,10 +82,9 @@ fn deburr(text: &str) -> String {
     return deunicode::deunicode(text);
 }
 
-fn shuffle_lines(text: &str) -> String {
+fn shuffle_lines(text: &str, rng: &mut StdRng) -> String {
     let mut lines: Vec<String> = text.lines().map(|x| x.to_string()).collect();
-    let mut rng = rand::rng();
-    lines.shuffle(&mut rng);
+    lines.shuffle(rng);
     return lines.join("\n");
 }
 
@@ -124,7 +125,7 @@ fn trim_input(text: &str) -> String {
 
 fn natural_sort_lines(text: &str) -> String {
     let mut lines: Vec<String> = text.lines().map(|x| x.to_string()).collect();
-    lines.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    lines.sort_by_key(|a| a.to_lowercase());
     return lines.join("\n");
 }
 
@@ -141,8 +142,8 @@ const LOREM_SECTIONS: &[&str] = &[
     "Commodo augue arcu dignissim velit aliquam imperdiet mollis. Semper vel class aptent taciti sociosqu ad litora. Cras eleifend turpis fames primis vulputate ornare sagittis. Orci varius natoque penatibus et magnis dis parturient. Proin libero feugiat tristique accumsan maecenas potenti ultricies. Eros lobortis nulla molestie mattis scelerisque maximus eget. Curabitur facilisi cubilia curae hac habitasse platea dictumst. Efficitur laoreet mauris pharetra vestibulum fusce dictum risus. Adipiscing elit quisque faucibus ex sapien vitae pellentesque. Consequat magna ante condimentum neque at luctus nibh. Pretium tellus duis convallis tempus leo eu aenean. Ligula congue sollicitudin erat viverra ac tincidunt nam.", 
 ];
 
-fn lorem_ipsum() -> String {
-    let choice = rand::random_range(0..6);
+fn lorem_ipsum(rng: &mut StdRng) -> String {
+    let choice = rng.random_range(0..LOREM_SECTIONS.len());
     return LOREM_SECTIONS[choice].to_string();
 }
 
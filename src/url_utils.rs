@@ -0,0 +1,334 @@
+use itertools::Itertools;
+use regex::{Captures, Regex};
+use serde_json::{json, Value};
+use unicode_script::{Script, UnicodeScript};
+use url::Url;
+
+pub enum UrlOp {
+    ParseUrl, AddQueryParam, RemoveQueryParam, SortQueryParams, StripTrackingParams,
+    NormalizeUrl, CanonicalizeUrl, ResolveUrl, ToPunycode, FromPunycode, CheckHomograph, Unknown,
+}
+
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid", "twclid",
+    "mc_cid", "mc_eid", "_ga", "_gl", "_hsenc", "_hsmi", "mkt_tok", "ref_src", "oly_anon_id",
+    "oly_enc_id", "vero_id",
+];
+
+/// Cyrillic and Greek letters that render (near) identically to Latin ones.
+const LATIN_LOOKALIKES: &str = "аеорсухіјѕԁһӏԛԝвкмнтАВЕКМНОРСТХЅІЈαορτυνκιΑΒΕΖΗΙΚΜΝΟΡΤΥΧ";
+
+/// Generic TLDs recognised for domains written without a scheme; two-letter country
+/// codes are accepted as a class.
+const GENERIC_TLDS: &[&str] = &[
+    "com", "net", "org", "info", "biz", "edu", "gov", "mil", "int", "arpa", "name", "pro",
+    "aero", "asia", "cat", "coop", "jobs", "mobi", "museum", "post", "tel", "travel", "xxx",
+    "app", "dev", "page", "blog", "shop", "store", "online", "site", "website", "tech", "cloud",
+    "xyz", "top", "club", "live", "news", "media", "email", "link", "digital", "network",
+    "systems", "software", "company", "agency", "group", "global", "world", "today", "space",
+    "studio", "design", "art", "bank", "insurance", "health", "law", "finance", "capital",
+    "money", "academy", "school", "university",
+];
+
+/// Internationalised TLDs in their Unicode form.
+const IDN_TLDS: &[&str] = &[
+    "рф", "рус", "укр", "бел", "срб", "мкд", "қаз", "мон", "бг", "ею", "ελ", "ευ", "中国", "中國",
+    "香港", "台湾", "台灣", "新加坡", "澳門", "公司", "网络", "网址", "みんな", "コム", "한국", "닷컴",
+    "ไทย", "भारत", "بھارت", "السعودية", "مصر", "امارات", "ایران", "الجزائر", "المغرب", "عمان",
+    "قطر", "сайт", "онлайн", "орг", "дети", "москва",
+];
+
+/// Script mixes that are normal within one label (UTS #39 "highly restrictive").
+const ALLOWED_SCRIPT_MIXES: &[&[Script]] = &[
+    &[Script::Latin, Script::Han, Script::Hiragana, Script::Katakana],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+fn str_to_url_op(arg: &str) -> UrlOp {
+    return match arg {
+        "parse_url" => UrlOp::ParseUrl,
+        "add_query_param" => UrlOp::AddQueryParam,
+        "remove_query_param" => UrlOp::RemoveQueryParam,
+        "sort_query_params" => UrlOp::SortQueryParams,
+        "strip_tracking_params" => UrlOp::StripTrackingParams,
+        "normalize_url" => UrlOp::NormalizeUrl,
+        "canonicalize_url" => UrlOp::CanonicalizeUrl,
+        "resolve_url" => UrlOp::ResolveUrl,
+        "to_punycode" => UrlOp::ToPunycode,
+        "from_punycode" => UrlOp::FromPunycode,
+        "check_homograph" => UrlOp::CheckHomograph,
+        _ => UrlOp::Unknown,
+    }
+}
+
+pub fn select_url_option(args: &Vec<String>) -> UrlOp {
+    let url_options: Vec<String> = vec!["parse_url", "add_query_param", "remove_query_param", "sort_query_params", "strip_tracking_params", "normalize_url", "canonicalize_url", "resolve_url", "to_punycode", "from_punycode", "check_homograph"].into_iter().map(|x| x.to_string()).collect();
+    for arg in args {
+        if url_options.contains(&arg.to_lowercase()) {
+            return str_to_url_op(arg);
+        }
+    }
+    return UrlOp::Unknown
+}
+
+/// `add_query_param` takes `--param=key=value` and `remove_query_param` takes `--param=key`,
+/// both repeatable. `resolve_url` takes `--base=<url>`.
+pub fn handle_url_operation(text: &str, op: UrlOp, args: &Vec<String>) -> String {
+    let params = crate::utils::get_option_values(args, "param");
+    return match op {
+        UrlOp::ParseUrl => parse_url(text),
+        UrlOp::AddQueryParam => map_urls(text, |url| add_query_params(url, &params)),
+        UrlOp::RemoveQueryParam => map_urls(text, |url| remove_query_params(url, &params)),
+        UrlOp::SortQueryParams => map_urls(text, sort_query_params),
+        UrlOp::StripTrackingParams => map_urls(text, strip_tracking_params),
+        UrlOp::NormalizeUrl => map_urls(text, normalize_url),
+        UrlOp::CanonicalizeUrl => map_urls(text, canonicalize_url),
+        UrlOp::ResolveUrl => {
+            let base = crate::utils::get_option_value(args, "base").expect("resolve_url requires --base=<url>.");
+            resolve_urls(text, &base)
+        },
+        UrlOp::ToPunycode => map_domains(text, |domain| !domain.is_ascii(), |domain| idna::domain_to_ascii(domain).ok()),
+        UrlOp::FromPunycode => map_domains(text, is_punycode, |domain| Some(idna::domain_to_unicode(domain).0)),
+        UrlOp::CheckHomograph => check_homographs(text),
+        UrlOp::Unknown => panic!("Unknown url operation specified."),
+    };
+}
+
+fn url_pattern() -> Regex {
+    return Regex::new(r#"[a-zA-Z][a-zA-Z0-9+.-]*://[^\s<>"'`]+"#).unwrap();
+}
+
+/// Splits a match into the URL itself and any trailing sentence punctuation,
+/// so `see https://example.com.` does not swallow the full stop.
+fn split_trailing(found: &str) -> (&str, &str) {
+    let mut end = found.trim_end_matches(['.', ',', ';', ':', '!', '?']).len();
+    let mut url = &found[..end];
+    while url.ends_with(')') && url.matches('(').count() < url.matches(')').count() {
+        end -= 1;
+        url = &found[..end];
+    }
+    return (url, &found[end..]);
+}
+
+/// Applies `f` to every URL found in the text, leaving everything else
+/// (including anything that fails to parse) untouched.
+fn map_urls<F: Fn(&mut Url)>(text: &str, f: F) -> String {
+    return url_pattern().replace_all(text, |caps: &Captures| {
+        let (raw, rest) = split_trailing(&caps[0]);
+        match Url::parse(raw) {
+            Ok(mut url) => {
+                f(&mut url);
+                format!("{}{}", url, rest)
+            },
+            Err(_) => caps[0].to_string(),
+        }
+    }).to_string();
+}
+
+fn url_to_json(url: &Url) -> Value {
+    let params: Vec<Value> = url.query_pairs().map(|(k, v)| json!([k, v])).collect();
+    return json!({
+        "url": url.as_str(),
+        "scheme": url.scheme(),
+        "username": url.username(),
+        "password": url.password(),
+        "host": url.host_str(),
+        "port": url.port(),
+        "port_or_default": url.port_or_known_default(),
+        "path": url.path(),
+        "path_segments": url.path_segments().map(|s| s.collect::<Vec<&str>>()),
+        "query": url.query(),
+        "query_params": params,
+        "fragment": url.fragment(),
+    });
+}
+
+/// A single URL is printed as one object; several URLs become an array.
+fn parse_url(text: &str) -> String {
+    let trimmed = text.trim();
+    let mut found: Vec<Value> = Vec::new();
+    if let Ok(url) = Url::parse(trimmed) {
+        found.push(url_to_json(&url));
+    }
+    else {
+        for m in url_pattern().find_iter(text) {
+            if let Ok(url) = Url::parse(split_trailing(m.as_str()).0) {
+                found.push(url_to_json(&url));
+            }
+        }
+    }
+    let result = match found.len() {
+        0 => return "No valid URLs found.".to_string(),
+        1 => found.remove(0),
+        _ => Value::Array(found),
+    };
+    return serde_json::to_string_pretty(&result).unwrap();
+}
+
+fn query_pairs(url: &Url) -> Vec<(String, String)> {
+    return url.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+}
+
+/// Rewrites the query from `pairs`, dropping the `?` entirely when nothing is left.
+fn set_query_pairs(url: &mut Url, pairs: &[(String, String)]) {
+    if pairs.is_empty() {
+        url.set_query(None);
+        return;
+    }
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+}
+
+fn add_query_params(url: &mut Url, params: &[String]) {
+    let mut pairs = query_pairs(url);
+    for param in params {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        pairs.push((key.to_string(), value.to_string()));
+    }
+    set_query_pairs(url, &pairs);
+}
+
+fn remove_query_params(url: &mut Url, keys: &[String]) {
+    let pairs: Vec<(String, String)> = query_pairs(url).into_iter().filter(|(k, _)| !keys.contains(k)).collect();
+    set_query_pairs(url, &pairs);
+}
+
+fn sort_query_params(url: &mut Url) {
+    if url.query().is_none() { return; }
+    let mut pairs = query_pairs(url);
+    pairs.sort();
+    set_query_pairs(url, &pairs);
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let lower = key.to_lowercase();
+    return lower.starts_with("utm_") || TRACKING_PARAMS.contains(&lower.as_str());
+}
+
+fn strip_tracking_params(url: &mut Url) {
+    if url.query().is_none() { return; }
+    let pairs: Vec<(String, String)> = query_pairs(url).into_iter().filter(|(k, _)| !is_tracking_param(k)).collect();
+    set_query_pairs(url, &pairs);
+}
+
+/// Parsing already lowercases the scheme and host, drops default ports and
+/// resolves `.`/`..` segments; this also removes an empty `?` or `#`.
+fn normalize_url(url: &mut Url) {
+    if url.query() == Some("") { url.set_query(None); }
+    if url.fragment() == Some("") { url.set_fragment(None); }
+}
+
+/// A normalised URL with tracking parameters and the fragment removed and the
+/// remaining query parameters sorted, suitable for comparing or deduplicating.
+fn canonicalize_url(url: &mut Url) {
+    normalize_url(url);
+    strip_tracking_params(url);
+    sort_query_params(url);
+    url.set_fragment(None);
+}
+
+/// Every non-empty line is treated as a (possibly relative) reference.
+fn resolve_urls(text: &str, base: &str) -> String {
+    let base = match Url::parse(base) {
+        Ok(url) => url,
+        Err(e) => return format!("Invalid base URL: {}", e),
+    };
+    return text.lines().map(|line| {
+        let trimmed = line.trim();
+        if trimmed.is_empty() { return line.to_string(); }
+        match base.join(trimmed) {
+            Ok(url) => url.to_string(),
+            Err(_) => line.to_string(),
+        }
+    }).collect::<Vec<String>>().join("\n");
+}
+
+/// Dot-separated labels of letters, marks, digits and hyphens. This also matches
+/// dotted words such as `file.txt`, so every match is checked with `is_domain`.
+fn domain_pattern() -> Regex {
+    let label = r"[\p{L}\p{M}\p{N}](?:[\p{L}\p{M}\p{N}-]*[\p{L}\p{M}\p{N}])?";
+    return Regex::new(&format!(r"{label}(?:\.{label})+")).unwrap();
+}
+
+/// A lowercase two-letter country code, a common generic TLD or an internationalised
+/// TLD, in Unicode or punycode form.
+fn is_known_tld(label: &str) -> bool {
+    if label != label.to_lowercase() {
+        return false;
+    }
+    return (label.len() == 2 && label.chars().all(|c| c.is_ascii_alphabetic()))
+        || GENERIC_TLDS.contains(&label)
+        || IDN_TLDS.contains(&label)
+        || label.starts_with("xn--");
+}
+
+/// A match counts as a domain when it is the host of a URL (`scheme://`), the domain
+/// of an email address or userinfo (`@`), or ends in a known TLD. Prose such as
+/// `Café.Bar` or file names such as `naïve.txt` are left alone.
+fn is_domain(text: &str, start: usize, domain: &str) -> bool {
+    let before = &text[..start];
+    if before.ends_with("://") || before.ends_with('@') {
+        return true;
+    }
+    return domain.rsplit('.').next().is_some_and(is_known_tld);
+}
+
+fn is_punycode(domain: &str) -> bool {
+    return domain.split('.').any(|label| label.to_lowercase().starts_with("xn--"));
+}
+
+/// Converts every domain accepted by `select`, leaving the rest of the text as written.
+fn map_domains<S: Fn(&str) -> bool, F: Fn(&str) -> Option<String>>(text: &str, select: S, convert: F) -> String {
+    return domain_pattern().replace_all(text, |caps: &Captures| {
+        let domain = &caps[0];
+        let start = caps.get(0).map_or(0, |m| m.start());
+        if !is_domain(text, start, domain) || !select(domain) { return domain.to_string(); }
+        convert(domain).unwrap_or(domain.to_string())
+    }).to_string();
+}
+
+fn label_scripts(label: &str) -> Vec<Script> {
+    let mut scripts: Vec<Script> = Vec::new();
+    for c in label.chars() {
+        let script = c.script();
+        if script != Script::Common && script != Script::Inherited && !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    return scripts;
+}
+
+/// Flags a label that mixes scripts outside the usual CJK combinations, or that
+/// is written entirely in lookalike Cyrillic or Greek letters (e.g. `аррӏе`).
+fn homograph_warning(label: &str) -> Option<String> {
+    let scripts = label_scripts(label);
+    let names: Vec<&str> = scripts.iter().map(|s| s.full_name()).collect();
+    if scripts.len() > 1 && !ALLOWED_SCRIPT_MIXES.iter().any(|allowed| scripts.iter().all(|s| allowed.contains(s))) {
+        return Some(format!("MIXED SCRIPT ({}) in \"{}\"", names.join(", "), label));
+    }
+    let lookalike = scripts.len() == 1
+        && matches!(scripts[0], Script::Cyrillic | Script::Greek)
+        && label.chars().filter(|c| c.is_alphabetic()).all(|c| LATIN_LOOKALIKES.contains(c));
+    if lookalike {
+        return Some(format!("CONFUSABLE ({} letters that look Latin) in \"{}\"", names[0], label));
+    }
+    return None;
+}
+
+/// Lists every internationalised domain in both forms with a verdict.
+fn check_homographs(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for m in domain_pattern().find_iter(text) {
+        let domain = m.as_str();
+        if !is_domain(text, m.start(), domain) || domain.is_ascii() && !is_punycode(domain) { continue; }
+        let unicode = idna::domain_to_unicode(domain).0;
+        let ascii = idna::domain_to_ascii(domain).unwrap_or(domain.to_string());
+        let warnings: Vec<String> = unicode.split('.').filter_map(homograph_warning).collect();
+        let verdict = if warnings.is_empty() { "OK".to_string() } else { warnings.join("; ") };
+        lines.push(format!("{} ({}): {}", unicode, ascii, verdict));
+    }
+    if lines.is_empty() {
+        return "No internationalised domains found.".to_string();
+    }
+    return lines.into_iter().unique().collect::<Vec<String>>().join("\n");
+}
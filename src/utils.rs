@@ -1,21 +1,92 @@
 use std::fs;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// `--options` that take a value, which may also be given as the next argument
+/// (`--schema s.json`). Every other `--option` is a switch.
+const VALUE_OPTIONS: &[&str] = &[
+    "base", "cipher", "classes", "count", "delimiter", "dialect", "hash", "indent", "kdf", "key", "length", "level",
+    "lines-between", "max-size", "name", "namespace", "param", "password", "password-file", "query", "schema", "secret", "seed", "shift",
+    "source-map", "targets", "width",
+];
 
 pub enum CommandFamily {
-    Casing, Encoding, Format, TextUtils, Conversion, Unknown,
+    Casing, Encoding, Format, TextUtils, Conversion, UrlUtils, Generator, Escape, Compression, Validation, JsonTools, Unknown,
 }
 
 pub fn find_file_path(args: &Vec<String>) -> Option<String> {
+    return find_file_paths(args).into_iter().next();
+}
+
+/// Every input file named on the command line, in order, for operations that
+/// compare or combine several documents.
+pub fn find_file_paths(args: &Vec<String>) -> Vec<String> {
     let flags = get_flags(args);
+    let mut result = Vec::new();
+    let mut flag_idx = None;
     if flags_contains(&flags, 'f') {
         let idx = get_flag_idx(&flags, 'f');
-        return Some(args[idx].clone());
+        result.push(args[idx].clone());
+        flag_idx = Some(idx);
     }
-    for arg in args {
-        if arg.contains(".") {
-            return Some(arg.clone());
+    let skipped = if flags_contains(&flags, 's') { Some(get_flag_idx(&flags, 's')) } else { None };
+    for (i, arg) in args.iter().enumerate().skip(1) {
+        let option_value = args[i - 1].strip_prefix("--").is_some_and(|name| VALUE_OPTIONS.contains(&name));
+        if arg.contains(".") && !arg.starts_with("--") && !option_value && skipped != Some(i) && flag_idx != Some(i) {
+            result.push(arg.clone());
+        }
+    }
+    return result;
+}
+
+/// Returns every value given for `--name`, as `--name=value` or `--name value`.
+pub fn get_option_values(args: &Vec<String>, name: &str) -> Vec<String> {
+    debug_assert!(VALUE_OPTIONS.contains(&name), "--{} is missing from VALUE_OPTIONS", name);
+    let key = format!("--{}", name);
+    let prefix = format!("{}=", key);
+    let mut result = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        if *arg == key {
+            match args.get(i + 1) {
+                Some(value) if !value.starts_with("--") => result.push(value.clone()),
+                _ => panic!("{} expects a value: {}=<value> or {} <value>.", key, key, key),
+            }
+        }
+        else if let Some(value) = arg.strip_prefix(&prefix) {
+            result.push(value.to_string());
         }
     }
-    return None;
+    return result;
+}
+
+pub fn get_option_value(args: &Vec<String>, name: &str) -> Option<String> {
+    return get_option_values(args, name).into_iter().next();
+}
+
+pub fn get_usize_option(args: &Vec<String>, name: &str, default: usize) -> usize {
+    return match get_option_value(args, name) {
+        Some(value) => value.parse().unwrap_or_else(|_| panic!("--{} expects a whole number, got '{}'.", name, value)),
+        None => default,
+    };
+}
+
+pub fn has_option(args: &Vec<String>, name: &str) -> bool {
+    return args.contains(&format!("--{}", name));
+}
+
+/// Indentation for the formatters: `--tabs`, or `--indent=<n>` spaces (two by default).
+pub fn get_indent(args: &Vec<String>) -> String {
+    if has_option(args, "tabs") {
+        return "\t".to_string();
+    }
+    return " ".repeat(get_usize_option(args, "indent", 2));
+}
+
+/// Every operation that uses randomness draws from this, so `--seed=<n>` makes output reproducible.
+pub fn get_rng(args: &Vec<String>) -> StdRng {
+    return match get_option_value(args, "seed") {
+        Some(seed) => StdRng::seed_from_u64(seed.parse().expect("--seed expects a whole number.")),
+        None => StdRng::from_os_rng(),
+    };
 }
 
 pub fn get_file_contents(args: &Vec<String>) -> String {
@@ -31,11 +102,27 @@ pub fn get_file_contents(args: &Vec<String>) -> String {
     std::process::exit(1);
 }
 
+/// Binary-safe counterpart of `get_file_contents`, also honouring `-s`.
+pub fn get_input_bytes(args: &Vec<String>) -> Vec<u8> {
+    let flags = get_flags(args);
+    if flags_contains(&flags, 's') {
+        return args[get_flag_idx(&flags, 's')].clone().into_bytes();
+    }
+    if let Some(path) = find_file_path(args) {
+        return match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(_) => panic!("Invalid file path."),
+        };
+    }
+    eprintln!("Unable to find file path.\nIf using a file with no extension, please use the -f flag.\nIf using a string, please use the -s flag.");
+    std::process::exit(1);
+}
+
 pub fn get_flags(args: &Vec<String>) -> Vec<(usize, char)> {
     let mut result = Vec::new();
-    let valid_flags = vec!['s', 'f', 'i', 'h'];
+    let valid_flags = ['s', 'f', 'i', 'h'];
     for (i, arg) in args.iter().enumerate() {
-        if arg.starts_with("-") {
+        if arg.starts_with("-") && !arg.starts_with("--") {
             let flags: Vec<char> = {
                 arg
                     .chars()
@@ -64,10 +151,16 @@ pub fn flags_contains(flags: &Vec<(usize, char)>, target: char) -> bool {
 
 pub fn get_command_family(args: &Vec<String>) -> CommandFamily {
     let casing_options: Vec<String> = vec!["upper", "lower", "title", "sponge", "snake", "camel", "kebab"].into_iter().map(|x| x.to_string()).collect();
-    let encoding_options: Vec<String> = vec!["rot13", "base64_encode", "base64_decode", "md5", "html_encode", "html_decode", "html_encode_all", "url_encode", "url_decode", "url_entity_encode", "url_entities_decode", "sha1", "sha256", "sha512"].into_iter().map(|x| x.to_string()).collect();
-    let format_options: Vec<String> = vec!["add_slashes", "remove_slashes", "format_json", "format_sql", "format_css", "format_xml", "minify_json", "minify_sql", "minify_css", "minify_xml"].into_iter().map(|x| x.to_string()).collect();
+    let encoding_options: Vec<String> = vec!["rot13", "rot_n", "rot47", "atbash", "vigenere_encode", "vigenere_decode", "xor_encode", "xor_decode", "morse_encode", "morse_decode", "nato", "magic", "identify", "base64_encode", "base64_decode", "md5", "html_encode", "html_encode_attribute", "html_encode_minimal", "html_encode_non_ascii", "html_decode", "html_encode_all", "url_encode", "url_encode_path", "url_encode_query", "url_encode_form", "url_encode_full", "url_decode", "url_decode_form", "url_entity_encode", "url_entities_decode", "jwt_decode", "encrypt", "decrypt", "sha1", "sha256", "sha512", "bcrypt_hash", "scrypt_hash", "argon2_hash", "pbkdf2_hash", "verify_password"].into_iter().map(|x| x.to_string()).collect();
+    let format_options: Vec<String> = vec!["add_slashes", "remove_slashes", "format_json", "format_sql", "format_css", "format_xml", "format_html", "format_yaml", "format_toml", "format_ini", "format_markdown_table", "format_graphql", "format_protobuf", "format_dockerfile", "minify_json", "minify_sql", "minify_css", "minify_xml", "minify_html", "minify_js", "minify_yaml", "minify_toml", "minify_ini"].into_iter().map(|x| x.to_string()).collect();
     let text_util_options: Vec<String> = vec!["defang", "refang", "deburr", "shuffle", "sum", "count", "collapse", "dedup", "sort", "trim", "natural_sort", "reverse", "lorem_ipsum", "md_quote", "replace_smart_quotes"].into_iter().map(|x| x.to_string()).collect();
-    let conversion_options: Vec<String> = vec!["ascii_to_hex", "hex_to_ascii", "yaml_to_json", "json_to_yaml", "date_to_timestamp", "date_to_utc", "binary_to_decimal", "decimal_to_binary", "json_to_query_string", "query_strong_to_json", "decimal_to_hex", "hex_to_decimal", "json_to_csv", "csv_to_json", "hex_to_rgb", "fish_path_hex_conv"].into_iter().map(|x| x.to_string()).collect();
+    let conversion_options: Vec<String> = vec!["ascii_to_hex", "hex_to_ascii", "yaml_to_json", "json_to_yaml", "date_to_timestamp", "date_to_utc", "binary_to_decimal", "decimal_to_binary", "json_to_query_string", "query_strong_to_json", "decimal_to_hex", "hex_to_decimal", "json_to_csv", "csv_to_json", "hex_to_rgb", "fish_path_hex_conv", "json5_to_json"].into_iter().map(|x| x.to_string()).collect();
+    let url_options: Vec<String> = vec!["parse_url", "add_query_param", "remove_query_param", "sort_query_params", "strip_tracking_params", "normalize_url", "canonicalize_url", "resolve_url", "to_punycode", "from_punycode", "check_homograph"].into_iter().map(|x| x.to_string()).collect();
+    let generator_options: Vec<String> = vec!["uuid", "uuid_v4", "uuid_v5", "uuid_v7", "ulid", "nanoid", "hex_token", "base64_token", "generate_password"].into_iter().map(|x| x.to_string()).collect();
+    let escape_options: Vec<String> = vec!["escape_unicode", "unescape_unicode", "escape_unicode_braced", "unescape_unicode_braced", "escape_hex", "unescape_hex", "escape_octal", "unescape_octal", "escape_unicode_name", "unescape_unicode_name", "escape_json", "unescape_json", "escape_c", "unescape_c", "escape_java", "unescape_java", "escape_python", "unescape_python", "escape_shell", "unescape_shell", "escape_sql", "unescape_sql", "escape_regex", "unescape_regex", "escape_csv", "unescape_csv", "escape_xml_attr", "unescape_xml_attr"].into_iter().map(|x| x.to_string()).collect();
+    let compression_options: Vec<String> = vec!["gzip_compress", "gzip_decompress", "zlib_compress", "zlib_decompress", "deflate_compress", "deflate_decompress", "bzip2_compress", "bzip2_decompress", "xz_compress", "xz_decompress", "zstd_compress", "zstd_decompress"].into_iter().map(|x| x.to_string()).collect();
+    let validation_options: Vec<String> = vec!["validate_json", "validate_yaml", "validate_xml", "validate_csv", "validate_toml", "validate_schema"].into_iter().map(|x| x.to_string()).collect();
+    let json_tool_options: Vec<String> = vec!["json_query", "json_diff", "json_patch", "json_merge_patch"].into_iter().map(|x| x.to_string()).collect();
     for arg in args {
         if casing_options.contains(&arg.to_lowercase()) {
             return CommandFamily::Casing;
@@ -84,6 +177,24 @@ pub fn get_command_family(args: &Vec<String>) -> CommandFamily {
         else if conversion_options.contains(&arg.to_lowercase()) {
             return CommandFamily::Conversion;
         }
+        else if url_options.contains(&arg.to_lowercase()) {
+            return CommandFamily::UrlUtils;
+        }
+        else if generator_options.contains(&arg.to_lowercase()) {
+            return CommandFamily::Generator;
+        }
+        else if escape_options.contains(&arg.to_lowercase()) {
+            return CommandFamily::Escape;
+        }
+        else if compression_options.contains(&arg.to_lowercase()) {
+            return CommandFamily::Compression;
+        }
+        else if validation_options.contains(&arg.to_lowercase()) {
+            return CommandFamily::Validation;
+        }
+        else if json_tool_options.contains(&arg.to_lowercase()) {
+            return CommandFamily::JsonTools;
+        }
     }
     return CommandFamily::Unknown;
 }
@@ -91,10 +202,16 @@ pub fn get_command_family(args: &Vec<String>) -> CommandFamily {
 pub fn print_commands() {
     println!("Available options:");
     let casing_options: Vec<String> = vec!["upper", "lower", "title", "sponge", "snake", "camel", "kebab"].into_iter().map(|x| x.to_string()).collect();
-    let encoding_options: Vec<String> = vec!["rot13", "base64_encode", "base64_decode", "md5", "html_encode", "html_decode", "html_encode_all", "url_encode", "url_decode", "url_entity_encode", "url_entities_decode", "sha1", "sha256", "sha512"].into_iter().map(|x| x.to_string()).collect();
-    let format_options: Vec<String> = vec!["add_slashes", "remove_slashes", "format_json", "format_sql", "format_css", "format_xml", "minify_json", "minify_sql", "minify_css", "minify_xml"].into_iter().map(|x| x.to_string()).collect();
+    let encoding_options: Vec<String> = vec!["rot13", "rot_n", "rot47", "atbash", "vigenere_encode", "vigenere_decode", "xor_encode", "xor_decode", "morse_encode", "morse_decode", "nato", "magic", "identify", "base64_encode", "base64_decode", "md5", "html_encode", "html_encode_attribute", "html_encode_minimal", "html_encode_non_ascii", "html_decode", "html_encode_all", "url_encode", "url_encode_path", "url_encode_query", "url_encode_form", "url_encode_full", "url_decode", "url_decode_form", "url_entity_encode", "url_entities_decode", "jwt_decode", "encrypt", "decrypt", "sha1", "sha256", "sha512", "bcrypt_hash", "scrypt_hash", "argon2_hash", "pbkdf2_hash", "verify_password"].into_iter().map(|x| x.to_string()).collect();
+    let format_options: Vec<String> = vec!["add_slashes", "remove_slashes", "format_json", "format_sql", "format_css", "format_xml", "format_html", "format_yaml", "format_toml", "format_ini", "format_markdown_table", "format_graphql", "format_protobuf", "format_dockerfile", "minify_json", "minify_sql", "minify_css", "minify_xml", "minify_html", "minify_js", "minify_yaml", "minify_toml", "minify_ini"].into_iter().map(|x| x.to_string()).collect();
     let text_util_options: Vec<String> = vec!["defang", "refang", "deburr", "shuffle", "sum", "count", "collapse", "dedup", "sort", "trim", "natural_sort", "reverse", "lorem_ipsum", "md_quote", "replace_smart_quotes"].into_iter().map(|x| x.to_string()).collect();
-    let conversion_options: Vec<String> = vec!["ascii_to_hex", "hex_to_ascii", "yaml_to_json", "json_to_yaml", "date_to_timestamp", "date_to_utc", "binary_to_decimal", "decimal_to_binary", "json_to_query_string", "query_strong_to_json", "decimal_to_hex", "hex_to_decimal", "json_to_csv", "csv_to_json", "hex_to_rgb", "fish_path_hex_conv"].into_iter().map(|x| x.to_string()).collect();
+    let conversion_options: Vec<String> = vec!["ascii_to_hex", "hex_to_ascii", "yaml_to_json", "json_to_yaml", "date_to_timestamp", "date_to_utc", "binary_to_decimal", "decimal_to_binary", "json_to_query_string", "query_strong_to_json", "decimal_to_hex", "hex_to_decimal", "json_to_csv", "csv_to_json", "hex_to_rgb", "fish_path_hex_conv", "json5_to_json"].into_iter().map(|x| x.to_string()).collect();
+    let url_options: Vec<String> = vec!["parse_url", "add_query_param", "remove_query_param", "sort_query_params", "strip_tracking_params", "normalize_url", "canonicalize_url", "resolve_url", "to_punycode", "from_punycode", "check_homograph"].into_iter().map(|x| x.to_string()).collect();
+    let generator_options: Vec<String> = vec!["uuid", "uuid_v4", "uuid_v5", "uuid_v7", "ulid", "nanoid", "hex_token", "base64_token", "generate_password"].into_iter().map(|x| x.to_string()).collect();
+    let escape_options: Vec<String> = vec!["escape_unicode", "unescape_unicode", "escape_unicode_braced", "unescape_unicode_braced", "escape_hex", "unescape_hex", "escape_octal", "unescape_octal", "escape_unicode_name", "unescape_unicode_name", "escape_json", "unescape_json", "escape_c", "unescape_c", "escape_java", "unescape_java", "escape_python", "unescape_python", "escape_shell", "unescape_shell", "escape_sql", "unescape_sql", "escape_regex", "unescape_regex", "escape_csv", "unescape_csv", "escape_xml_attr", "unescape_xml_attr"].into_iter().map(|x| x.to_string()).collect();
+    let compression_options: Vec<String> = vec!["gzip_compress", "gzip_decompress", "zlib_compress", "zlib_decompress", "deflate_compress", "deflate_decompress", "bzip2_compress", "bzip2_decompress", "xz_compress", "xz_decompress", "zstd_compress", "zstd_decompress"].into_iter().map(|x| x.to_string()).collect();
+    let validation_options: Vec<String> = vec!["validate_json", "validate_yaml", "validate_xml", "validate_csv", "validate_toml", "validate_schema"].into_iter().map(|x| x.to_string()).collect();
+    let json_tool_options: Vec<String> = vec!["json_query", "json_diff", "json_patch", "json_merge_patch"].into_iter().map(|x| x.to_string()).collect();
     println!("CASING:");
     for option in casing_options {
         println!(" - {}", option);
@@ -115,4 +232,28 @@ pub fn print_commands() {
     for option in conversion_options {
         println!(" - {}", option);
     }
+    println!("URL UTILS:");
+    for option in url_options {
+        println!(" - {}", option);
+    }
+    println!("GENERATORS:");
+    for option in generator_options {
+        println!(" - {}", option);
+    }
+    println!("ESCAPES:");
+    for option in escape_options {
+        println!(" - {}", option);
+    }
+    println!("COMPRESSION:");
+    for option in compression_options {
+        println!(" - {}", option);
+    }
+    println!("VALIDATION:");
+    for option in validation_options {
+        println!(" - {}", option);
+    }
+    println!("JSON TOOLS:");
+    for option in json_tool_options {
+        println!(" - {}", option);
+    }
 }
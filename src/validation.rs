@@ -0,0 +1,306 @@
+use std::fs;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use crate::{json, utils};
+
+pub enum ValidationOp {
+    ValidateJson, ValidateYaml, ValidateXml, ValidateCsv, ValidateToml, ValidateSchema, Unknown,
+}
+
+fn str_to_validation_op(arg: &str) -> ValidationOp {
+    return match arg {
+        "validate_json" => ValidationOp::ValidateJson,
+        "validate_yaml" => ValidationOp::ValidateYaml,
+        "validate_xml" => ValidationOp::ValidateXml,
+        "validate_csv" => ValidationOp::ValidateCsv,
+        "validate_toml" => ValidationOp::ValidateToml,
+        "validate_schema" => ValidationOp::ValidateSchema,
+        _ => ValidationOp::Unknown,
+    }
+}
+
+pub fn select_validation_option(args: &Vec<String>) -> ValidationOp {
+    let validation_options: Vec<String> = vec!["validate_json", "validate_yaml", "validate_xml", "validate_csv", "validate_toml", "validate_schema"].into_iter().map(|x| x.to_string()).collect();
+    for arg in args {
+        if validation_options.contains(&arg.to_lowercase()) {
+            return str_to_validation_op(arg);
+        }
+    }
+    return ValidationOp::Unknown
+}
+
+struct ValidationError {
+    message: String,
+    line: usize,
+    column: usize,
+}
+
+/// `Ok("Valid <format>.")` when the input is clean, otherwise `Err` with the errors
+/// found, each with its location, for `main` to print to stderr before exiting with
+/// status 1 so the operation can gate a commit hook or CI step.
+pub fn handle_validation_operation(text: &str, op: ValidationOp, args: &Vec<String>) -> Result<String, String> {
+    let (name, errors) = match op {
+        ValidationOp::ValidateJson => ("JSON", located(text, validate_json(text, utils::has_option(args, "lenient")))),
+        ValidationOp::ValidateYaml => ("YAML", located(text, validate_yaml(text))),
+        ValidationOp::ValidateXml => ("XML", located(text, validate_xml(text))),
+        ValidationOp::ValidateCsv => ("CSV", located(text, validate_csv(text, args))),
+        ValidationOp::ValidateToml => ("TOML", located(text, validate_toml(text))),
+        ValidationOp::ValidateSchema => ("document", validate_schema(text, args)),
+        ValidationOp::Unknown => panic!("Unknown validation operation specified."),
+    };
+    if errors.is_empty() {
+        return Ok(format!("Valid {}.", name));
+    }
+    let plural = if errors.len() == 1 { "" } else { "s" };
+    return Err(format!("{}\n{} error{} found in {}.", errors.join("\n"), errors.len(), plural, name));
+}
+
+fn located(text: &str, errors: Vec<ValidationError>) -> Vec<String> {
+    return errors.iter().map(|error| report(text, error)).collect();
+}
+
+/// rustc-style report: the message, its location and the offending line with a caret.
+fn report(text: &str, error: &ValidationError) -> String {
+    let source = text.lines().nth(error.line.saturating_sub(1)).unwrap_or("");
+    let gutter = " ".repeat(error.line.to_string().len());
+    let caret_pad: String = source.chars()
+        .take(error.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    return format!(
+        "error: {}\n{}--> line {}, column {}\n{} |\n{} | {}\n{} | {}^\n",
+        error.message, gutter, error.line, error.column, gutter, error.line, source, gutter, caret_pad,
+    );
+}
+
+/// Converts a byte offset into a 1-based line and character column.
+pub fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    return (line, column);
+}
+
+fn at_offset(text: &str, offset: usize, message: String) -> ValidationError {
+    let (line, column) = line_column(text, offset);
+    return ValidationError { message, line, column };
+}
+
+/// JSON first, then YAML (a superset of JSON), so either can be validated against a schema.
+fn read_json_or_yaml(text: &str) -> Result<serde_json::Value, String> {
+    return match serde_json::from_str(text) {
+        Ok(value) => Ok(value),
+        Err(_) => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+    };
+}
+
+/// Validates against the JSON Schema (draft 2020-12) in `--schema=<file>`, which may
+/// itself be JSON or YAML. Each violation is listed with the JSON Pointer of the
+/// offending value and the schema keyword that rejected it.
+fn validate_schema(text: &str, args: &Vec<String>) -> Vec<String> {
+    let schema_path = match utils::get_option_value(args, "schema") {
+        Some(path) => path,
+        None => panic!("validate_schema needs a schema: --schema=<file>."),
+    };
+    let schema_text = match fs::read_to_string(&schema_path) {
+        Ok(text) => text,
+        Err(e) => panic!("Unable to read schema file {}: {}", schema_path, e),
+    };
+    let schema = read_json_or_yaml(&schema_text).unwrap_or_else(|e| panic!("Unable to parse schema {}: {}", schema_path, e));
+    let validator = jsonschema::draft202012::new(&schema).unwrap_or_else(|e| panic!("Invalid schema {}: {}", schema_path, e));
+    let instance = match read_json_or_yaml(text) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("error: Unable to parse input as JSON or YAML: {}", e)],
+    };
+    return validator.iter_errors(&instance).map(|error| {
+        let path = error.instance_path().to_string();
+        format!(
+            "error: {}\n  at: {}\n  schema: {}\n",
+            error,
+            if path.is_empty() { "(root)" } else { &path },
+            error.schema_path(),
+        )
+    }).collect();
+}
+
+/// Strict RFC 8259 unless `--lenient` is given, which also accepts JSONC and JSON5.
+/// Parsing stops at the first syntax error, since the structure after it is unknown.
+fn validate_json(text: &str, lenient: bool) -> Vec<ValidationError> {
+    let result = if lenient { json::parse_lenient(text) } else { json::parse(text) };
+    return match result {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![ValidationError { message: e.message, line: e.line, column: e.column }],
+    };
+}
+
+/// Splits a stream at its `---` markers into documents, each with the 0-based line it
+/// starts on. Directives (`%YAML`) stay with the document that follows them.
+fn yaml_documents(text: &str) -> Vec<(usize, String)> {
+    let mut documents: Vec<(usize, String)> = vec![(0, String::new())];
+    for (idx, line) in text.lines().enumerate() {
+        let marker = line == "---" || line.starts_with("--- ") || line.starts_with("---\t");
+        let only_directives = documents.last().is_some_and(|(_, document)| {
+            document.lines().all(|l| l.trim().is_empty() || l.starts_with('%') || l.trim_start().starts_with('#'))
+        });
+        if marker && !only_directives {
+            documents.push((idx, String::new()));
+        }
+        if let Some((_, document)) = documents.last_mut() {
+            document.push_str(line);
+            document.push('\n');
+        }
+    }
+    return documents;
+}
+
+/// Every document in a multi-document stream is checked on its own, so an error in
+/// one does not hide errors in the next; within a document only the first is found.
+fn validate_yaml(text: &str) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    for (start, document) in yaml_documents(text) {
+        if let Err(e) = serde_yaml::from_str::<serde_yaml::Value>(&document) {
+            let (line, column) = match e.location() {
+                Some(location) => (location.line(), location.column()),
+                None => (1, 1),
+            };
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or(&message).to_string();
+            errors.push(ValidationError { message, line: start + line, column });
+        }
+    }
+    return errors;
+}
+
+/// Structural problems (mismatched and unclosed tags, extra roots, text outside the
+/// root) are all reported; a syntax error ends the check, since the reader cannot
+/// reliably find the next tag after it.
+fn validate_xml(text: &str) -> Vec<ValidationError> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().check_end_names = false;
+    let mut errors = Vec::new();
+    let mut open: Vec<(String, usize)> = Vec::new();
+    let mut roots = 0;
+    loop {
+        let offset = reader.buffer_position() as usize;
+        let root = match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                open.push((String::from_utf8_lossy(e.name().as_ref()).to_string(), offset));
+                open.len() == 1
+            },
+            Ok(Event::Empty(_)) => open.is_empty(),
+            Ok(Event::End(e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                match open.iter().rposition(|(open_name, _)| *open_name == name) {
+                    Some(idx) => {
+                        for (unclosed, at) in open.drain(idx + 1..) {
+                            errors.push(at_offset(text, at, format!("Element <{}> is not closed before </{}>", unclosed, name)));
+                        }
+                        open.pop();
+                    },
+                    None => errors.push(at_offset(text, offset, format!("Closing tag </{}> has no matching start tag", name))),
+                }
+                false
+            },
+            Ok(Event::Text(e)) if open.is_empty() && !e.iter().all(|b| b.is_ascii_whitespace()) => {
+                errors.push(at_offset(text, offset, "Text outside the root element".to_string()));
+                false
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => false,
+            Err(e) => {
+                errors.push(at_offset(text, reader.error_position() as usize, e.to_string()));
+                return errors;
+            },
+        };
+        if root {
+            roots += 1;
+            if roots > 1 {
+                errors.push(at_offset(text, offset, "Only one root element is allowed".to_string()));
+            }
+        }
+    }
+    for (name, offset) in open {
+        errors.push(at_offset(text, offset, format!("Element <{}> is never closed", name)));
+    }
+    if roots == 0 && errors.is_empty() {
+        errors.push(ValidationError { message: "No root element".to_string(), line: 1, column: 1 });
+    }
+    return errors;
+}
+
+/// Byte offset where field `field` of the record starting at `start` begins, or where
+/// the record ends if it has fewer fields. Quoted delimiters and line breaks are skipped.
+fn csv_field_offset(text: &str, start: usize, delimiter: u8, field: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut quoted = false;
+    let mut current = 0;
+    let mut idx = start;
+    while idx < bytes.len() && current < field {
+        match bytes[idx] {
+            b'"' => quoted = !quoted,
+            b'\n' | b'\r' if !quoted => return idx,
+            b if b == delimiter && !quoted => current += 1,
+            _ => {},
+        }
+        idx += 1;
+    }
+    return idx;
+}
+
+/// Reports every malformed record and every row whose field count differs from the
+/// header, pointing at the first extra field or at the end of a short row;
+/// `--delimiter=<c>` changes the separator.
+fn validate_csv(text: &str, args: &Vec<String>) -> Vec<ValidationError> {
+    let delimiter = match utils::get_option_value(args, "delimiter") {
+        Some(d) if d.len() == 1 => d.as_bytes()[0],
+        Some(d) => panic!("--delimiter expects a single character, got '{}'.", d),
+        None => b',',
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut errors = Vec::new();
+    let mut expected: Option<usize> = None;
+    for record in reader.records() {
+        match record {
+            Ok(record) => {
+                let start = record.position().map_or(0, |p| p.byte() as usize);
+                match expected {
+                    None => expected = Some(record.len()),
+                    Some(count) if count != record.len() => {
+                        let offset = csv_field_offset(text, start, delimiter, count);
+                        errors.push(at_offset(text, offset, format!("Expected {} fields but found {}", count, record.len())));
+                    },
+                    _ => {},
+                }
+            },
+            Err(e) => {
+                let start = e.position().map_or(0, |p| p.byte() as usize);
+                let field = match e.kind() {
+                    csv::ErrorKind::Utf8 { err, .. } => err.field(),
+                    _ => 0,
+                };
+                errors.push(at_offset(text, csv_field_offset(text, start, delimiter, field), e.to_string()));
+                if !matches!(e.kind(), csv::ErrorKind::Utf8 { .. }) { break; }
+            },
+        }
+    }
+    return errors;
+}
+
+/// The TOML parser stops at the first error, so at most one is reported.
+fn validate_toml(text: &str) -> Vec<ValidationError> {
+    return match toml::from_str::<toml::Table>(text) {
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            let offset = e.span().map_or(0, |span| span.start);
+            vec![at_offset(text, offset, e.message().to_string())]
+        },
+    };
+}